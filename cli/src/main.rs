@@ -0,0 +1,216 @@
+use std::io::Read;
+
+use prelude::*;
+
+const USAGE: &str = "usage: cli [-v | -vv] DAY [INPUT] [--explain] [--dot] [--cross-check]
+           [--render] [--expansion N] [--trace]
+
+Runs both parts of DAY on the contents of INPUT, or on stdin if INPUT is
+omitted or \"-\".
+
+    -v, -vv     print DAY's debug (or trace) diagnostics to stderr; RUST_LOG
+                overrides this, e.g. RUST_LOG=day_08=trace,day_05=debug
+    --explain   day 7: show why each hand placed where it did
+    --dot       day 8: print the network as Graphviz DOT instead of solving it
+    --cross-check
+                day 10: also count the enclosed tiles by flood fill
+    --render    day 10: draw the loop, with the tiles it encloses marked I
+                day 13: draw each pattern's part 2 mirror and smudge
+    --expansion N
+                day 11: also sum the distances when each empty row and column
+                grows to N of them
+    --trace     day 15: list the boxes after every step";
+
+#[derive(Default)]
+struct Args {
+    day: u8,
+    input: Option<String>,
+    verbosity: u8,
+    explain: bool,
+    dot: bool,
+    cross_check: bool,
+    render: bool,
+    expansion: Option<u64>,
+    trace: bool,
+}
+
+fn parse_args() -> anyhow::Result<Args> {
+    let mut args = Args::default();
+    let mut day = None;
+
+    let mut argv = std::env::args().skip(1);
+    while let Some(arg) = argv.next() {
+        match arg.as_str() {
+            "--explain" => args.explain = true,
+            "--dot" => args.dot = true,
+            "--cross-check" => args.cross_check = true,
+            "--render" => args.render = true,
+            "--trace" => args.trace = true,
+            "--expansion" => {
+                let n = argv.next().context("--expansion needs a number")?;
+                args.expansion = Some(n.parse().context("--expansion must be a number")?);
+            }
+            "-v" | "--verbose" => args.verbosity += 1,
+            "-vv" => args.verbosity += 2,
+            "-h" | "--help" => {
+                println!("{USAGE}");
+                std::process::exit(0);
+            }
+            flag if flag.starts_with('-') && flag != "-" => {
+                anyhow::bail!("unknown flag {flag:?}")
+            }
+            _ if day.is_none() => day = Some(arg.parse().context("DAY must be a number")?),
+            _ if args.input.is_none() => args.input = Some(arg),
+            _ => anyhow::bail!("unexpected argument {arg:?}"),
+        }
+    }
+
+    args.day = day.ok_or_else(|| anyhow::anyhow!("missing DAY"))?;
+
+    for (given, flag, days) in [
+        (args.explain, "--explain", &[7][..]),
+        (args.dot, "--dot", &[8]),
+        (args.cross_check, "--cross-check", &[10]),
+        (args.render, "--render", &[10, 13]),
+        (args.expansion.is_some(), "--expansion", &[11]),
+        (args.trace, "--trace", &[15]),
+    ] {
+        if given && !days.contains(&args.day) {
+            anyhow::bail!("{flag} doesn't apply to day {}", args.day);
+        }
+    }
+
+    Ok(args)
+}
+
+fn read_input(path: Option<&str>) -> anyhow::Result<String> {
+    match path {
+        None | Some("-") => {
+            let mut input = String::new();
+            std::io::stdin()
+                .read_to_string(&mut input)
+                .context("reading stdin")?;
+            Ok(input)
+        }
+        Some(path) => std::fs::read_to_string(path).with_context(|| format!("reading {path}")),
+    }
+}
+
+fn run<D: Day>(input: &str) -> anyhow::Result<D> {
+    let solution = D::new(input);
+    println!("Part 1: {}", solution.part1()?);
+    println!("Part 2: {}", solution.part2()?);
+    Ok(solution)
+}
+
+fn explain_day_07(solution: &day_07::Solution) {
+    for (name, explained) in [
+        ("Part 1", solution.explain(&day_07::Standard)),
+        ("Part 2", solution.explain(&day_07::Jokers)),
+    ] {
+        println!();
+        println!("{name}:");
+        for e in explained {
+            let cards = e.ranked.hand.cards().map(day_07::Card::label);
+            print!(
+                "{:>5} {} {:?}",
+                e.ranked.rank,
+                String::from_iter(cards),
+                e.ranked.hand_type
+            );
+            if let Some(substitute) = e.substitute {
+                print!(", wildcards are {}", substitute.label());
+            }
+            if let Some(position) = e.tiebreak {
+                print!(
+                    ", beats the hand below at card {} ({})",
+                    position + 1,
+                    cards[position]
+                );
+            }
+            println!();
+        }
+    }
+}
+
+fn main() -> anyhow::Result<()> {
+    let args = match parse_args() {
+        Ok(args) => args,
+        Err(e) => {
+            eprintln!("{e}\n\n{USAGE}");
+            std::process::exit(2);
+        }
+    };
+    diagnostics::init_stderr(diagnostics::filter_for(
+        &format!("day_{:02}", args.day),
+        args.verbosity,
+    ));
+    let input = read_input(args.input.as_deref())?;
+
+    match args.day {
+        1 => drop(run::<day_01::Solution>(&input)?),
+        2 => drop(run::<day_02::Solution>(&input)?),
+        3 => drop(run::<day_03::Solution>(&input)?),
+        4 => drop(run::<day_04::Solution>(&input)?),
+        5 => drop(run::<day_05::Solution>(&input)?),
+        6 => drop(run::<day_06::Solution>(&input)?),
+        7 => {
+            let solution = run::<day_07::Solution>(&input)?;
+            if args.explain {
+                explain_day_07(&solution);
+            }
+        }
+        8 if args.dot => print!("{}", <day_08::Solution as Day>::new(&input).to_dot()),
+        8 => drop(run::<day_08::Solution>(&input)?),
+        9 => drop(run::<day_09::Solution>(&input)?),
+        10 => {
+            let solution = run::<day_10::Solution>(&input)?;
+            if args.cross_check {
+                println!("Flood fill: {}", solution.enclosed_area_flood_fill()?);
+            }
+            if args.render {
+                let options = day_10::RenderOptions {
+                    only_loop: true,
+                    mark_interior: true,
+                };
+                print!("\n{}", solution.render(options)?);
+            }
+        }
+        11 => {
+            let solution = run::<day_11::Solution>(&input)?;
+            if let Some(expansion) = args.expansion {
+                println!(
+                    "Expanded {expansion} times: {}",
+                    solution.sum_distances(expansion)?
+                );
+            }
+        }
+        12 => drop(run::<day_12::Solution>(&input)?),
+        13 => {
+            let solution = run::<day_13::Solution>(&input)?;
+            if args.render {
+                for pattern in solution.patterns() {
+                    for reflection in pattern.reflections(1) {
+                        if reflection.mismatches() == 1 {
+                            print!("\n{}", pattern.render(&reflection));
+                        }
+                    }
+                }
+            }
+        }
+        14 => drop(run::<day_14::Solution>(&input)?),
+        15 => {
+            let solution = run::<day_15::Solution>(&input)?;
+            if args.trace {
+                print!("\n{}", solution.trace()?);
+            }
+        }
+        16 => drop(run::<day_16::Solution>(&input)?),
+        17 => drop(run::<day_17::Solution>(&input)?),
+        18 => drop(run::<day_18::Solution>(&input)?),
+        19 => drop(run::<day_19::Solution>(&input)?),
+        day => anyhow::bail!("there is no day {day}"),
+    }
+
+    Ok(())
+}
@@ -96,7 +96,7 @@ impl Day for Solution {
                 let number = [first, last];
                 let number =
                     std::str::from_utf8(&number).context("two ASCII digits must be valid UTF-8")?;
-                log::debug!("password {} -> digits {}", p_str, number);
+                tracing::debug!(password = p_str, digits = number);
                 number
                     .parse::<u64>()
                     .context("two ASCII digits should parse as a u64 successfully")
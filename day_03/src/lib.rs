@@ -19,7 +19,7 @@ impl Day for Solution {
                     continue;
                 }
 
-                log::debug!("symbol at {:?}: {:?}", (row, col), c);
+                tracing::debug!(row, col, symbol = ?c, "found symbol");
 
                 // we're on a symbol, so check all around it for a digit
                 for (x, y) in adjacent_including_diagonal(&self.0, row, col) {
@@ -41,11 +41,11 @@ impl Day for Solution {
                         let last = (y..string.len())
                             .find(|&i| !string[i].is_ascii_digit())
                             .unwrap_or(string.len());
-                        log::debug!("{first} {last}");
+                        tracing::trace!(first, last, "digits span");
 
                         let part_number = std::str::from_utf8(&string[first..last])
                             .context("digits should be UTF-8")?;
-                        log::debug!("found part number: {:?}", part_number);
+                        tracing::debug!(part_number, "found part number");
 
                         sum += part_number
                             .parse::<u64>()
@@ -76,7 +76,7 @@ impl Day for Solution {
 
                 let mut visited = HashSet::new();
                 let mut part_numbers = Vec::new();
-                log::debug!("symbol at {:?}: {:?}", (row, col), c);
+                tracing::debug!(row, col, symbol = ?c, "found symbol");
 
                 // we're on a symbol, so check all around it for a digit
                 for (x, y) in adjacent_including_diagonal(&self.0, row, col) {
@@ -98,11 +98,11 @@ impl Day for Solution {
                         let last = (y..string.len())
                             .find(|&i| !string[i].is_ascii_digit())
                             .unwrap_or(string.len());
-                        log::debug!("{first} {last}");
+                        tracing::trace!(first, last, "digits span");
 
                         let part_number = std::str::from_utf8(&string[first..last])
                             .context("digits should be UTF-8")?;
-                        log::debug!("found part number: {:?}", part_number);
+                        tracing::debug!(part_number, "found part number");
 
                         part_numbers.push(
                             part_number
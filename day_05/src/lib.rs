@@ -1,22 +1,290 @@
-use std::{cmp::min, collections::BTreeMap};
+use std::{
+    cmp::min,
+    collections::{BTreeMap, VecDeque},
+    fmt::{self, Display},
+};
 
 use prelude::*;
 
-type Map = BTreeMap<u64, (u64, u64)>;
+/// A piecewise-linear function on `u64`, where every piece has a slope of one.
+///
+/// The pieces are stored as `source start => (destination start, length)`,
+/// exactly like the lines of the almanac.  Any value not covered by a piece
+/// maps to itself.  The whole-domain operations (`pieces`, `compose`, `invert`
+/// and `preimage`) consider the domain to be `0..u64::MAX`.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct RangeMap(BTreeMap<u64, (u64, u64)>);
+
+impl RangeMap {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Build a map from `(source_start, destination_start, length)` pieces,
+    /// dropping the ones that are identity or empty and merging pieces that
+    /// continue one another, so that equal functions compare equal.
+    pub fn from_pieces(pieces: impl IntoIterator<Item = (u64, u64, u64)>) -> Self {
+        let mut map = Self::new();
+        let mut pending: Option<(u64, u64, u64)> = None;
+
+        for (from, to, count) in pieces
+            .into_iter()
+            .filter(|&(from, to, count)| from != to && count != 0)
+            .sorted()
+        {
+            match &mut pending {
+                Some((p_from, p_to, p_count))
+                    if *p_from + *p_count == from && *p_to + *p_count == to =>
+                {
+                    *p_count += count;
+                }
+                _ => {
+                    if let Some((p_from, p_to, p_count)) = pending {
+                        map.insert(p_from, p_to, p_count);
+                    }
+                    pending = Some((from, to, count));
+                }
+            }
+        }
+
+        if let Some((p_from, p_to, p_count)) = pending {
+            map.insert(p_from, p_to, p_count);
+        }
+
+        map
+    }
+
+    /// Map `count` values starting at `from` onto `count` values starting at
+    /// `to`.  Pieces must not overlap one another.
+    pub fn insert(&mut self, from: u64, to: u64, count: u64) {
+        self.0.insert(from, (to, count));
+    }
+
+    /// The explicitly-mapped pieces, as `(source_start, destination_start,
+    /// length)`, in order of their source.
+    pub fn segments(&self) -> impl Iterator<Item = (u64, u64, u64)> + '_ {
+        self.0.iter().map(|(&from, &(to, count))| (from, to, count))
+    }
+
+    /// Every piece of the function, including the identity pieces between the
+    /// explicit ones, so that the sources exactly cover `0..u64::MAX`.
+    pub fn pieces(&self) -> Vec<(u64, u64, u64)> {
+        let mut res = vec![];
+        let mut k = 0;
+
+        for (from, to, count) in self.segments() {
+            if from > k {
+                res.push((k, k, from - k));
+            }
+            res.push((from, to, count));
+            k = from + count;
+        }
+
+        if k < u64::MAX {
+            res.push((k, k, u64::MAX - k));
+        }
+
+        res
+    }
+
+    pub fn get(&self, k: u64) -> u64 {
+        let previous = self.0.range(..=k).next_back();
+        if let Some((from, (to, count))) = previous {
+            assert!(k >= *from, "looked up {}, found {}", k, from);
+            let offset = k - from;
+            if offset < *count {
+                to + offset
+            } else {
+                k
+            }
+        } else {
+            k
+        }
+    }
+
+    /// Map the `target_count` values starting at `k`, returning the
+    /// `(start, count)` ranges that they land on.
+    pub fn get_ranges(&self, mut k: u64, mut target_count: u64) -> Vec<(u64, u64)> {
+        let mut res = vec![];
+
+        // Some() if our start actually exists in a range
+        let previous = self
+            .0
+            .range(..=k)
+            .next_back()
+            .filter(|(&from, &(_to, count))| {
+                assert!(k >= from);
+                k - from < count
+            });
+        if let Some((from, (to, count))) = previous {
+            let offset = k - from;
+            let end_count = min(target_count, count - offset);
+            res.push((to + offset, end_count));
+            target_count -= end_count;
+            k += end_count;
+        }
+
+        while target_count != 0 {
+            // invariant: k is either exactly the beginning of a mapped range, or
+            // fully outside one.  We must have fully exhausted any partial range
+            // before here.
+            tracing::trace!(k, target_count, "starting on range");
+
+            let next = self.0.range(k..).next();
+            if let Some((&from, &(next_to, next_count))) = next {
+                if from > k {
+                    // k is outside any mapped range
+                    // these are the values from k to the next segment
+                    let identity_count = min(target_count, from - k);
+                    res.push((k, identity_count));
+                    k += identity_count;
+                    target_count -= identity_count;
+                } else {
+                    // these are the values within the this segment
+                    let segment_count = min(target_count, next_count);
+                    res.push((next_to, segment_count));
+                    k += segment_count;
+                    target_count -= segment_count;
+                }
+            } else {
+                // there is no next segment in the map, so write out the last
+                // segment as mapping target_count values from k to k.
+                res.push((k, target_count));
+                break;
+            }
+        }
+
+        res
+    }
+
+    pub fn get_all_ranges(&self, ranges: Vec<(u64, u64)>) -> Vec<(u64, u64)> {
+        tracing::debug!(?ranges, "mapping ranges");
+
+        ranges
+            .into_iter()
+            .flat_map(|(k, count)| self.get_ranges(k, count).into_iter())
+            .collect()
+    }
+
+    /// The map that applies `self` and then `next`.
+    pub fn compose(&self, next: &RangeMap) -> RangeMap {
+        let mut pieces = vec![];
+
+        for (from, to, count) in self.pieces() {
+            // get_ranges returns the ranges in the same order as the values it
+            // was given, so the source side just advances by each count.
+            let mut from = from;
+            for (next_to, next_count) in next.get_ranges(to, count) {
+                pieces.push((from, next_to, next_count));
+                from += next_count;
+            }
+        }
+
+        RangeMap::from_pieces(pieces)
+    }
+
+    /// The inverse function, if `self` is a bijection.
+    pub fn invert(&self) -> anyhow::Result<RangeMap> {
+        let mut pieces = self
+            .pieces()
+            .into_iter()
+            .map(|(from, to, count)| (to, from, count))
+            .collect_vec();
+        pieces.sort();
+
+        // the sources were a partition of the domain, so the function is a
+        // bijection exactly when the destinations are too.
+        let mut k = 0;
+        for &(to, _from, count) in &pieces {
+            anyhow::ensure!(
+                to == k,
+                "not invertible: {} is reached {}",
+                min(to, k),
+                if to > k {
+                    "by nothing"
+                } else {
+                    "more than once"
+                }
+            );
+            k = to + count;
+        }
+
+        Ok(RangeMap::from_pieces(pieces))
+    }
+
+    /// The `(start, count)` ranges of every source value that maps into the
+    /// `count` values starting at `start`, in order and merged where adjacent.
+    pub fn preimage(&self, start: u64, count: u64) -> Vec<(u64, u64)> {
+        let end = start.saturating_add(count);
+        let mut res: Vec<(u64, u64)> = vec![];
+
+        for (from, to, piece_count) in self.pieces() {
+            let lo = std::cmp::max(start, to);
+            let hi = min(end, to + piece_count);
+            if lo < hi {
+                res.push((from + (lo - to), hi - lo));
+            }
+        }
+
+        res.sort();
+        res.into_iter().fold(vec![], |mut acc, (k, count)| {
+            match acc.last_mut() {
+                Some((last, last_count)) if *last + *last_count == k => *last_count += count,
+                _ => acc.push((k, count)),
+            }
+            acc
+        })
+    }
+
+    /// The explicit segments as CSV, with a header row.
+    pub fn to_csv(&self) -> String {
+        let mut res = "source_start,destination_start,length\n".to_owned();
+        for (from, to, count) in self.segments() {
+            res += &format!("{from},{to},{count}\n");
+        }
+        res
+    }
+}
+
+/// Writes the explicit segments as lines of `destination source length`, just
+/// like the body of an almanac map.
+impl Display for RangeMap {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (from, to, count) in self.segments() {
+            writeln!(f, "{to} {from} {count}")?;
+        }
+        Ok(())
+    }
+}
+
+/// One `X-to-Y map:` section of the almanac.
+pub struct CategoryMap {
+    source: String,
+    destination: String,
+    map: RangeMap,
+}
+
+impl CategoryMap {
+    pub fn source(&self) -> &str {
+        &self.source
+    }
+
+    pub fn destination(&self) -> &str {
+        &self.destination
+    }
+
+    pub fn map(&self) -> &RangeMap {
+        &self.map
+    }
+}
 
 pub struct Solution {
     seeds: Vec<u64>,
-    seed_to_soil: Map,
-    soil_to_fertilizer: Map,
-    fertilizer_to_water: Map,
-    water_to_light: Map,
-    light_to_temperature: Map,
-    temperature_to_humidity: Map,
-    humidity_to_location: Map,
+    maps: Vec<CategoryMap>,
 }
 
-fn parse_map<'a>(it: impl Iterator<Item = &'a str>) -> Map {
-    let mut map = Map::new();
+fn parse_map<'a>(it: impl Iterator<Item = &'a str>) -> RangeMap {
+    let mut map = RangeMap::new();
 
     let triples = it
         .take_while(|x| !x.is_empty())
@@ -30,86 +298,100 @@ fn parse_map<'a>(it: impl Iterator<Item = &'a str>) -> Map {
         .map(|junk: [u64; 3]| (junk[0], junk[1], junk[2]));
 
     for (to, from, count) in triples {
-        map.insert(from, (to, count));
+        map.insert(from, to, count);
     }
 
     map
 }
 
-fn get(map: &Map, k: u64) -> u64 {
-    let previous = map.range(..=k).next_back();
-    if let Some((from, (to, count))) = previous {
-        assert!(k >= *from, "looked up {}, found {}", k, from);
-        let offset = k - from;
-        if offset <= *count {
-            to + offset
-        } else {
-            k
-        }
-    } else {
-        k
+impl Solution {
+    /// The maps in the order they appeared in the almanac.
+    pub fn maps(&self) -> &[CategoryMap] {
+        &self.maps
     }
-}
 
-fn get_ranges(map: &Map, mut k: u64, mut target_count: u64) -> Vec<(u64, u64)> {
-    let mut res = vec![];
-
-    // Some() if our start actually exists in a range
-    let previous = map
-        .range(..=k)
-        .next_back()
-        .filter(|(&from, &(_to, count))| {
-            assert!(k >= from);
-            k - from <= count
-        });
-    if let Some((from, (to, count))) = previous {
-        let offset = k - from;
-        let end_count = min(target_count, count - offset);
-        res.push((to + offset, end_count));
-        target_count -= end_count;
-        k += end_count;
-    }
-
-    while target_count != 0 {
-        // invariant: k is either exactly the beginning of a mapped range, or
-        // fully outside one.  We must have fully exhausted any partial range
-        // before here.
-        log::debug!("starting on k = {}, count = {}", k, target_count);
-
-        let next = map.range(k..).next();
-        if let Some((&from, &(next_to, next_count))) = next {
-            if from > k {
-                // k is outside any mapped range
-                // these are the values from k to the next segment
-                let identity_count = min(target_count, from - k);
-                res.push((k, identity_count));
-                k += identity_count;
-                target_count -= identity_count;
-            } else {
-                // these are the values within the this segment
-                let segment_count = min(target_count, next_count);
-                res.push((next_to, segment_count));
-                k += segment_count;
-                target_count -= segment_count;
+    /// Find the sequence of maps that converts a `source` category into a
+    /// `destination` category, e.g. "soil" to "humidity".
+    fn path(&self, source: &str, destination: &str) -> anyhow::Result<Vec<&RangeMap>> {
+        // breadth-first search over categories, remembering which map got us to
+        // each one so the chain can be reconstructed afterward.
+        let mut came_from: HashMap<&str, &CategoryMap> = HashMap::new();
+        let mut queue = VecDeque::from([source]);
+
+        while let Some(category) = queue.pop_front() {
+            if category == destination {
+                break;
+            }
+
+            for next in self.maps.iter().filter(|m| m.source == category) {
+                if next.destination != source && !came_from.contains_key(&*next.destination) {
+                    came_from.insert(&next.destination, next);
+                    queue.push_back(&next.destination);
+                }
             }
-        } else {
-            // there is no next segment in the map, so write out the last
-            // segment as mapping target_count values from k to k.
-            res.push((k, target_count));
-            break;
         }
+
+        let mut path = vec![];
+        let mut category = destination;
+        while category != source {
+            let map = came_from.get(category).ok_or_else(|| {
+                anyhow::anyhow!("no chain of maps from {source:?} to {destination:?}")
+            })?;
+            path.push(&map.map);
+            category = &map.source;
+        }
+        path.reverse();
+
+        Ok(path)
     }
 
-    res
-}
+    /// Convert a single `source` number into its `destination` number.
+    pub fn lookup(&self, source: &str, destination: &str, k: u64) -> anyhow::Result<u64> {
+        let path = self.path(source, destination)?;
 
-fn get_all_ranges(map: &Map, ranges: Vec<(u64, u64)>) -> Vec<(u64, u64)> {
-    log::debug!("ranges: {:?}", ranges);
+        Ok(path.into_iter().fold(k, |idx, map| {
+            let idx = map.get(idx);
+            tracing::trace!(idx);
+            idx
+        }))
+    }
 
-    ranges
-        .into_iter()
-        .flat_map(|(k, count)| get_ranges(map, k, count).into_iter())
-        .collect()
+    /// Convert `(start, count)` ranges of `source` numbers into the
+    /// `destination` ranges they cover.
+    pub fn lookup_ranges(
+        &self,
+        source: &str,
+        destination: &str,
+        ranges: Vec<(u64, u64)>,
+    ) -> anyhow::Result<Vec<(u64, u64)>> {
+        let path = self.path(source, destination)?;
+
+        Ok(path
+            .into_iter()
+            .fold(ranges, |ranges, map| map.get_all_ranges(ranges)))
+    }
+
+    /// Compose the chain of maps from `source` to `destination` into a single
+    /// map.
+    pub fn compose(&self, source: &str, destination: &str) -> anyhow::Result<RangeMap> {
+        let path = self.path(source, destination)?;
+
+        Ok(path
+            .into_iter()
+            .fold(RangeMap::new(), |composed, map| composed.compose(map)))
+    }
+
+    /// Find the `source` ranges that land in the `count` `destination` values
+    /// starting at `start`, e.g. which seeds reach locations `0..100`.
+    pub fn preimage(
+        &self,
+        source: &str,
+        destination: &str,
+        start: u64,
+        count: u64,
+    ) -> anyhow::Result<Vec<(u64, u64)>> {
+        Ok(self.compose(source, destination)?.preimage(start, count))
+    }
 }
 
 impl Day for Solution {
@@ -121,58 +403,39 @@ impl Day for Solution {
             .split_whitespace()
             .skip(1)
             .map(|seed| {
-                log::debug!("{:?}", seed);
+                tracing::trace!(seed, "parsed seed");
                 seed.parse().expect("seed not an integer?")
             })
             .collect();
-        assert_eq!(lines.next().unwrap(), "");
-
-        assert_eq!(lines.next().unwrap(), "seed-to-soil map:");
-        let seed_to_soil = parse_map(&mut lines);
-        assert_eq!(lines.next().unwrap(), "soil-to-fertilizer map:");
-        let soil_to_fertilizer = parse_map(&mut lines);
-        assert_eq!(lines.next().unwrap(), "fertilizer-to-water map:");
-        let fertilizer_to_water = parse_map(&mut lines);
-        assert_eq!(lines.next().unwrap(), "water-to-light map:");
-        let water_to_light = parse_map(&mut lines);
-        assert_eq!(lines.next().unwrap(), "light-to-temperature map:");
-        let light_to_temperature = parse_map(&mut lines);
-        assert_eq!(lines.next().unwrap(), "temperature-to-humidity map:");
-        let temperature_to_humidity = parse_map(&mut lines);
-        assert_eq!(lines.next().unwrap(), "humidity-to-location map:");
-        let humidity_to_location = parse_map(&mut lines);
-
-        Solution {
-            seeds,
-            seed_to_soil,
-            soil_to_fertilizer,
-            fertilizer_to_water,
-            water_to_light,
-            light_to_temperature,
-            temperature_to_humidity,
-            humidity_to_location,
+
+        let mut maps = vec![];
+        while let Some(header) = lines.next() {
+            if header.is_empty() {
+                continue;
+            }
+
+            let (source, destination) = header
+                .strip_suffix(" map:")
+                .and_then(|name| name.split_once("-to-"))
+                .unwrap_or_else(|| panic!("unexpected map header {header:?}"));
+            let map = parse_map(&mut lines);
+
+            maps.push(CategoryMap {
+                source: source.to_owned(),
+                destination: destination.to_owned(),
+                map,
+            });
         }
+
+        Solution { seeds, maps }
     }
 
     fn part1(&self) -> anyhow::Result<u64> {
         let mut result = u64::MAX;
 
         for &seed in &self.seeds {
-            log::debug!("seed {}", seed);
-            let idx = get(&self.seed_to_soil, seed);
-            log::debug!("idx {}", idx);
-            let idx = get(&self.soil_to_fertilizer, idx);
-            log::debug!("idx {}", idx);
-            let idx = get(&self.fertilizer_to_water, idx);
-            log::debug!("idx {}", idx);
-            let idx = get(&self.water_to_light, idx);
-            log::debug!("idx {}", idx);
-            let idx = get(&self.light_to_temperature, idx);
-            log::debug!("idx {}", idx);
-            let idx = get(&self.temperature_to_humidity, idx);
-            log::debug!("idx {}", idx);
-            let idx = get(&self.humidity_to_location, idx);
-            log::debug!("idx {}", idx);
+            tracing::debug!(seed);
+            let idx = self.lookup("seed", "location", seed)?;
 
             result = std::cmp::min(result, idx);
         }
@@ -187,13 +450,7 @@ impl Day for Solution {
             .tuples()
             .map(|(&k, &count)| (k, count))
             .collect_vec();
-        let ranges = get_all_ranges(&self.seed_to_soil, ranges);
-        let ranges = get_all_ranges(&self.soil_to_fertilizer, ranges);
-        let ranges = get_all_ranges(&self.fertilizer_to_water, ranges);
-        let ranges = get_all_ranges(&self.water_to_light, ranges);
-        let ranges = get_all_ranges(&self.light_to_temperature, ranges);
-        let ranges = get_all_ranges(&self.temperature_to_humidity, ranges);
-        let ranges = get_all_ranges(&self.humidity_to_location, ranges);
+        let ranges = self.lookup_ranges("seed", "location", ranges)?;
 
         ranges
             .into_iter()
@@ -205,10 +462,12 @@ impl Day for Solution {
 
 #[cfg(test)]
 mod tests {
+    use proptest::prelude::*;
+
     use super::*;
 
     fn init() {
-        let _ = env_logger::builder().is_test(true).try_init();
+        diagnostics::init_test();
     }
 
     #[test]
@@ -220,6 +479,90 @@ mod tests {
         assert_eq!(example.part2().unwrap(), 46);
     }
 
+    #[test]
+    fn intermediate_categories() {
+        init();
+
+        let example = Solution::new(EXAMPLE);
+        // seed 79 is soil 81, fertilizer 81, water 81, light 74, temperature
+        // 78, humidity 78, location 82.
+        assert_eq!(example.lookup("soil", "humidity", 81).unwrap(), 78);
+        assert_eq!(example.lookup("water", "light", 81).unwrap(), 74);
+        assert_eq!(example.lookup("seed", "seed", 79).unwrap(), 79);
+        assert!(example.lookup("location", "seed", 82).is_err());
+        assert!(example.lookup("seed", "mulch", 79).is_err());
+    }
+
+    #[test]
+    fn composed_example() {
+        init();
+
+        let example = Solution::new(EXAMPLE);
+        let composed = example.compose("seed", "location").unwrap();
+        assert_eq!(
+            [79, 14, 55, 13].map(|seed| composed.get(seed)),
+            [82, 43, 86, 35]
+        );
+        assert_eq!(
+            composed
+                .get_all_ranges(vec![(79, 14), (55, 13)])
+                .into_iter()
+                .map(|(start, _)| start)
+                .min(),
+            Some(46)
+        );
+
+        // every map in the example happens to be a permutation
+        let inverse = composed.invert().unwrap();
+        assert_eq!(inverse.get(82), 79);
+        assert_eq!(inverse.compose(&composed), RangeMap::new());
+
+        let seeds = example.preimage("seed", "location", 46, 1).unwrap();
+        assert_eq!(seeds, vec![(82, 1)]);
+        let seeds = example.preimage("seed", "location", 0, 100).unwrap();
+        assert_eq!(seeds.iter().map(|(_, count)| count).sum::<u64>(), 100);
+    }
+
+    #[test]
+    fn export_composed() {
+        init();
+
+        let example = Solution::new(EXAMPLE);
+        let composed = example.compose("seed", "location").unwrap();
+        let almanac = composed.to_string();
+        assert_eq!(almanac.lines().count(), 20);
+        assert!(almanac.starts_with("22 0 14\n43 14 1\n36 15 7\n"));
+        assert!(composed
+            .to_csv()
+            .starts_with("source_start,destination_start,length\n0,22,14\n14,43,1\n"));
+
+        // the almanac format should parse back into the same function
+        let reparsed = Solution::new(&format!(
+            "seeds: 79 14 55 13\n\nseed-to-location map:\n{composed}"
+        ));
+        assert_eq!(reparsed.maps()[0].map(), &composed);
+        assert_eq!(reparsed.part1().unwrap(), 35);
+        assert_eq!(reparsed.part2().unwrap(), 46);
+    }
+
+    #[test]
+    fn reordered_maps() {
+        init();
+
+        // move the last section to the front, and add a map that doesn't
+        // participate in the seed-to-location chain.
+        let (rest, last) = EXAMPLE.rsplit_once("\n\n").unwrap();
+        let (seeds, rest) = rest.split_once("\n\n").unwrap();
+        let reordered = format!("{seeds}\n\n{last}\nwater-to-mulch map:\n1 2 3\n\n{rest}\n");
+
+        let example = Solution::new(&reordered);
+        assert_eq!(example.maps()[0].source(), "humidity");
+        assert_eq!(example.maps()[1].destination(), "mulch");
+        assert_eq!(example.part1().unwrap(), 35);
+        assert_eq!(example.part2().unwrap(), 46);
+        assert_eq!(example.lookup("seed", "mulch", 79).unwrap(), 81);
+    }
+
     #[test]
     fn personal_input() {
         init();
@@ -229,6 +572,121 @@ mod tests {
         assert_eq!(solution.part2().unwrap(), 69323688);
     }
 
+    /// The original forward lookup, walking the almanac's `(destination, source, length)` lines
+    /// directly so that it shares nothing with `RangeMap`.
+    fn forward(lines: &[(u64, u64, u64)], x: u64) -> u64 {
+        lines
+            .iter()
+            .find(|&&(_, from, count)| (from..from + count).contains(&x))
+            .map_or(x, |&(to, from, _)| to + (x - from))
+    }
+
+    fn build(lines: &[(u64, u64, u64)]) -> RangeMap {
+        let text = lines
+            .iter()
+            .map(|(to, from, count)| format!("{to} {from} {count}\n"))
+            .collect::<String>();
+        parse_map(text.lines())
+    }
+
+    fn arbitrary_map() -> impl Strategy<Value = Vec<(u64, u64, u64)>> {
+        prop::collection::vec((0..50u64, 1..50u64, 0..1000u64), 0..8).prop_map(|segments| {
+            let mut lines = vec![];
+            let mut from = 0;
+            for (gap, count, to) in segments {
+                from += gap;
+                lines.push((to, from, count));
+                from += count;
+            }
+            lines
+        })
+    }
+
+    /// Shuffle some blocks around within `0..sum of lengths`.
+    fn permutation_map() -> impl Strategy<Value = Vec<(u64, u64, u64)>> {
+        prop::collection::vec(1..50u64, 1..8)
+            .prop_flat_map(|lengths| (Just(lengths.clone()), Just(lengths).prop_shuffle()))
+            .prop_map(|(sources, destinations)| {
+                // blocks can have equal lengths, so pair them up by position
+                // in the shuffled list rather than by value.
+                let starts = |lengths: &[u64]| {
+                    lengths
+                        .iter()
+                        .scan(0, |k, &count| {
+                            *k += count;
+                            Some(*k - count)
+                        })
+                        .collect_vec()
+                };
+                let source_starts = starts(&sources);
+                let destination_starts = starts(&destinations);
+
+                let mut used = vec![false; destinations.len()];
+                let mut lines = vec![];
+                for (i, &count) in sources.iter().enumerate() {
+                    let j = (0..destinations.len())
+                        .find(|&j| !used[j] && destinations[j] == count)
+                        .unwrap();
+                    used[j] = true;
+                    lines.push((destination_starts[j], source_starts[i], count));
+                }
+                lines
+            })
+    }
+
+    proptest! {
+        #[test]
+        fn get_matches_lines(lines in arbitrary_map()) {
+            let map = build(&lines);
+            for x in 0..1500 {
+                prop_assert_eq!(map.get(x), forward(&lines, x));
+            }
+        }
+
+        #[test]
+        fn ranges_match_lines(lines in arbitrary_map(), k in 0..1500u64, count in 0..500u64) {
+            let values = build(&lines)
+                .get_ranges(k, count)
+                .into_iter()
+                .flat_map(|(start, count)| start..start + count)
+                .collect_vec();
+            prop_assert_eq!(values, (k..k + count).map(|x| forward(&lines, x)).collect_vec());
+        }
+
+        #[test]
+        fn compose_matches_lines(first in arbitrary_map(), second in arbitrary_map()) {
+            let composed = build(&first).compose(&build(&second));
+            for x in 0..1500 {
+                prop_assert_eq!(composed.get(x), forward(&second, forward(&first, x)));
+            }
+        }
+
+        #[test]
+        fn invert_matches_lines(lines in permutation_map()) {
+            let inverse = build(&lines).invert().unwrap();
+            for x in 0..500 {
+                prop_assert_eq!(inverse.get(forward(&lines, x)), x);
+            }
+        }
+
+        #[test]
+        fn invert_rejects_collisions(lines in arbitrary_map()) {
+            let hit = (0..1500).map(|x| forward(&lines, x)).collect::<HashSet<_>>();
+            if hit.len() < 1500 {
+                prop_assert!(build(&lines).invert().is_err());
+            }
+        }
+
+        #[test]
+        fn preimage_matches_lines(lines in arbitrary_map(), start in 0..1500u64, count in 0..500u64) {
+            let preimage = build(&lines).preimage(start, count);
+            for x in 0..1500 {
+                let inside = preimage.iter().any(|&(k, n)| (k..k + n).contains(&x));
+                prop_assert_eq!(inside, (start..start + count).contains(&forward(&lines, x)));
+            }
+        }
+    }
+
     static EXAMPLE: &str = "seeds: 79 14 55 13
 
 seed-to-soil map:
@@ -1,38 +1,16 @@
-use std::cmp::Ordering::*;
-
 use prelude::*;
 
 #[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
-struct Card(u8);
-
-impl std::cmp::Ord for Card {
-    fn cmp(&self, other: &Self) -> std::cmp::Ordering {
-        // 2 through 9 will sort normally, but let's assign TJQKA to arbitrarily
-        // high values.
-        fn map(x: u8) -> u8 {
-            match x {
-                b'2'..=b'9' => x,
-                b'T' => 210,
-                b'J' => 211,
-                b'Q' => 212,
-                b'K' => 213,
-                b'A' => 214,
-                _ => panic!("invalid card"),
-            }
-        }
-
-        map(self.0).cmp(&map(other.0))
-    }
-}
+pub struct Card(u8);
 
-impl PartialOrd for Card {
-    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
-        Some(self.cmp(other))
+impl Card {
+    pub fn label(self) -> char {
+        self.0 as char
     }
 }
 
-#[derive(Eq, Ord, PartialEq, PartialOrd)]
-enum Type {
+#[derive(Clone, Copy, Debug, Eq, Ord, PartialEq, PartialOrd)]
+pub enum Type {
     HighCard,
     OnePair,
     TwoPair,
@@ -43,23 +21,11 @@ enum Type {
 }
 use Type::*;
 
-#[derive(Clone, Eq)]
-struct Hand {
-    cards: [Card; 5],
-    bet: u64,
-}
-
-impl Hand {
-    fn get_type(&self) -> Type {
-        let mut counts = HashMap::new();
-        for x in self.cards {
-            *counts.entry(x).or_default() += 1
-        }
-
-        let mut counts = counts.values().collect_vec();
-        counts.sort_by_key(|&k| std::cmp::Reverse(k));
-
-        match &counts[..] {
+impl Type {
+    /// The type of a hand whose cards form groups of these sizes, largest
+    /// first.
+    fn from_counts(counts: &[u8]) -> Type {
+        match counts {
             [5] => FiveOfAKind,
             [4, 1] => FourOfAKind,
             [3, 2] => FullHouse,
@@ -72,96 +38,198 @@ impl Hand {
     }
 }
 
-impl Ord for Hand {
-    fn cmp(&self, other: &Self) -> std::cmp::Ordering {
-        match self.get_type().cmp(&other.get_type()) {
-            core::cmp::Ordering::Equal => self.cards.cmp(&other.cards),
-            ord => ord,
-        }
-    }
-}
+/// The rules that decide how hands are ordered.
+pub trait Ruleset {
+    /// How strong a single card is when breaking ties between hands of the
+    /// same type.
+    fn card_strength(&self, card: Card) -> u8;
 
-impl PartialOrd for Hand {
-    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
-        Some(self.cmp(other))
+    /// Whether `card` pretends to be whatever makes the hand the strongest.
+    fn is_wildcard(&self, _card: Card) -> bool {
+        false
     }
-}
 
-impl PartialEq for Hand {
-    fn eq(&self, other: &Self) -> bool {
-        self.cmp(other) == Equal
+    /// The card that the wildcards in a hand pretend to be, or `None` if there
+    /// aren't any wildcards.
+    fn substitute(&self, cards: &[Card; 5]) -> Option<Card> {
+        if !cards.iter().any(|&x| self.is_wildcard(x)) {
+            return None;
+        }
+
+        let mut counts = HashMap::new();
+        for &x in cards.iter().filter(|&&x| !self.is_wildcard(x)) {
+            *counts.entry(x).or_insert(0) += 1;
+        }
+
+        // add the wildcards to the highest-order card to make it the best hand
+        // possible.  If every card is wild, they may as well stay themselves.
+        counts
+            .into_iter()
+            .max_by_key(|&(card, count)| (count, self.card_strength(card)))
+            .map(|(card, _count)| card)
+            .or(Some(cards[0]))
     }
-}
 
-#[derive(Eq, PartialEq)]
-struct Joker<T>(T);
+    fn hand_type(&self, cards: &[Card; 5]) -> Type {
+        let substitute = self.substitute(cards);
 
-impl Joker<Hand> {
-    fn get_type(&self) -> Type {
         let mut counts = HashMap::new();
-        for x in self.0.cards {
+        for &x in cards {
+            let x = if self.is_wildcard(x) {
+                substitute.unwrap_or(x)
+            } else {
+                x
+            };
             *counts.entry(x).or_default() += 1
         }
 
-        let jokers = counts.remove(&Card(b'J')).unwrap_or(0);
-
-        let mut counts = counts.into_values().collect_vec();
+        let mut counts: Vec<u8> = counts.into_values().collect_vec();
         counts.sort_by_key(|&k| std::cmp::Reverse(k));
 
-        // add the jokers to the highest-order card to make it the best hand possible
-        if counts.is_empty() {
-            counts.push(jokers);
-        } else {
-            counts[0] += jokers;
-        }
+        Type::from_counts(&counts)
+    }
 
-        match &counts[..] {
-            [5] => FiveOfAKind,
-            [4, 1] => FourOfAKind,
-            [3, 2] => FullHouse,
-            [3, 1, 1] => ThreeOfAKind,
-            [2, 2, 1] => TwoPair,
-            [2, 1, 1, 1] => OnePair,
-            [1, 1, 1, 1, 1] => HighCard,
-            _ => panic!("unexpected counts {:?}", counts),
-        }
+    /// How strong a hand type is; hands are compared by this before their
+    /// cards.
+    fn type_strength(&self, hand_type: Type) -> u8 {
+        hand_type as u8
     }
 }
 
-impl Ord for Joker<Hand> {
-    fn cmp(&self, other: &Self) -> std::cmp::Ordering {
-        match self.get_type().cmp(&other.get_type()) {
-            core::cmp::Ordering::Equal => self.0.cards.map(Joker).cmp(&other.0.cards.map(Joker)),
-            ord => ord,
+/// The rules from part 1.
+pub struct Standard;
+
+impl Ruleset for Standard {
+    fn card_strength(&self, card: Card) -> u8 {
+        match card.0 {
+            b'2'..=b'9' => card.0 - b'0',
+            b'T' => 10,
+            b'J' => 11,
+            b'Q' => 12,
+            b'K' => 13,
+            b'A' => 14,
+            _ => panic!("invalid card"),
         }
     }
 }
 
-impl PartialOrd for Joker<Hand> {
-    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
-        Some(self.cmp(other))
-    }
-}
+/// The rules from part 2, where `J` is a joker: wild, but the weakest card when
+/// breaking ties.
+pub struct Jokers;
 
-impl Ord for Joker<Card> {
-    fn cmp(&self, other: &Self) -> std::cmp::Ordering {
-        match (self.0 .0, other.0 .0) {
-            (b'J', b'J') => Equal,
-            (b'J', _) => Less,
-            (_, b'J') => Greater,
-            (x, y) => Card(x).cmp(&Card(y)),
+impl Ruleset for Jokers {
+    fn card_strength(&self, card: Card) -> u8 {
+        match card.0 {
+            b'J' => 1,
+            _ => Standard.card_strength(card),
         }
     }
+
+    fn is_wildcard(&self, card: Card) -> bool {
+        card.0 == b'J'
+    }
 }
 
-impl PartialOrd for Joker<Card> {
-    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
-        Some(self.cmp(other))
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct Hand {
+    cards: [Card; 5],
+    bet: u64,
+}
+
+impl Hand {
+    pub fn cards(&self) -> [Card; 5] {
+        self.cards
+    }
+
+    pub fn bet(&self) -> u64 {
+        self.bet
     }
 }
 
+/// Where a hand placed under a particular ruleset.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct RankedHand {
+    pub hand: Hand,
+    pub hand_type: Type,
+    /// 1 for the weakest hand
+    pub rank: u64,
+}
+
+/// Why a hand placed where it did.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct Explanation {
+    pub ranked: RankedHand,
+    /// The card that any wildcards pretended to be.
+    pub substitute: Option<Card>,
+    /// The first position where this hand's cards beat those of the hand
+    /// ranked just below it, if that hand had the same type.
+    pub tiebreak: Option<usize>,
+}
+
 pub struct Solution(Vec<Hand>);
 
+impl Solution {
+    /// Every hand, weakest first, along with its type and rank.
+    pub fn rank(&self, rules: &impl Ruleset) -> Vec<RankedHand> {
+        let mut hands = self
+            .0
+            .iter()
+            .map(|&hand| (hand, rules.hand_type(&hand.cards)))
+            .collect_vec();
+        hands.sort_by_key(|(hand, hand_type)| {
+            (
+                rules.type_strength(*hand_type),
+                hand.cards.map(|card| rules.card_strength(card)),
+            )
+        });
+
+        hands
+            .into_iter()
+            .enumerate()
+            .map(|(i, (hand, hand_type))| RankedHand {
+                hand,
+                hand_type,
+                rank: i as u64 + 1,
+            })
+            .collect()
+    }
+
+    /// Like [`Solution::rank`], but also explains each hand's placement.
+    pub fn explain(&self, rules: &impl Ruleset) -> Vec<Explanation> {
+        let ranked = self.rank(rules);
+
+        let tiebreaks =
+            std::iter::once(None).chain(ranked.iter().tuple_windows().map(|(weaker, stronger)| {
+                if weaker.hand_type != stronger.hand_type {
+                    return None;
+                }
+                weaker
+                    .hand
+                    .cards
+                    .iter()
+                    .zip(stronger.hand.cards.iter())
+                    .position(|(&a, &b)| rules.card_strength(a) != rules.card_strength(b))
+            }));
+
+        ranked
+            .iter()
+            .zip(tiebreaks)
+            .map(|(&ranked, tiebreak)| Explanation {
+                ranked,
+                substitute: rules.substitute(&ranked.hand.cards),
+                tiebreak,
+            })
+            .collect()
+    }
+
+    pub fn total_winnings(&self, rules: &impl Ruleset) -> u64 {
+        self.rank(rules)
+            .into_iter()
+            .map(|ranked| ranked.hand.bet * ranked.rank)
+            .sum()
+    }
+}
+
 impl Day for Solution {
     fn new(input: &str) -> Solution {
         Solution(
@@ -181,23 +249,11 @@ impl Day for Solution {
     }
 
     fn part1(&self) -> anyhow::Result<u64> {
-        let mut hands = self.0.clone();
-        hands.sort();
-        Ok(hands
-            .into_iter()
-            .enumerate()
-            .map(|(rank, hand)| hand.bet * (rank as u64 + 1))
-            .sum())
+        Ok(self.total_winnings(&Standard))
     }
 
     fn part2(&self) -> anyhow::Result<u64> {
-        let mut hands = self.0.iter().cloned().map(Joker).collect_vec();
-        hands.sort();
-        Ok(hands
-            .into_iter()
-            .enumerate()
-            .map(|(rank, hand)| hand.0.bet * (rank as u64 + 1))
-            .sum())
+        Ok(self.total_winnings(&Jokers))
     }
 }
 
@@ -212,6 +268,110 @@ mod test {
         assert_eq!(example.part2().unwrap(), 5905);
     }
 
+    #[test]
+    fn example_ranks() {
+        let example = Solution::new(EXAMPLE);
+
+        let ranked = example.rank(&Standard);
+        assert_eq!(
+            ranked
+                .iter()
+                .map(|r| (r.hand.bet(), r.hand_type, r.rank))
+                .collect_vec(),
+            vec![
+                (765, OnePair, 1),
+                (220, TwoPair, 2),
+                (28, TwoPair, 3),
+                (684, ThreeOfAKind, 4),
+                (483, ThreeOfAKind, 5),
+            ]
+        );
+
+        let ranked = example.rank(&Jokers);
+        assert_eq!(
+            ranked
+                .iter()
+                .map(|r| (r.hand.bet(), r.hand_type, r.rank))
+                .collect_vec(),
+            vec![
+                (765, OnePair, 1),
+                (28, TwoPair, 2),
+                (684, FourOfAKind, 3),
+                (483, FourOfAKind, 4),
+                (220, FourOfAKind, 5),
+            ]
+        );
+    }
+
+    #[test]
+    fn example_explanations() {
+        let example = Solution::new(EXAMPLE);
+
+        let explained = example.explain(&Jokers);
+        assert_eq!(
+            explained
+                .iter()
+                .map(|e| (
+                    e.ranked.hand.bet(),
+                    e.substitute.map(Card::label),
+                    e.tiebreak
+                ))
+                .collect_vec(),
+            vec![
+                (765, None, None),
+                (28, None, None),
+                (684, Some('5'), None),
+                // QQQJA beats T55J5 at the very first card
+                (483, Some('Q'), Some(0)),
+                (220, Some('T'), Some(0)),
+            ]
+        );
+
+        let explained = example.explain(&Standard);
+        // KK677 and KTJJT are both two pair, and only differ at the second card
+        assert_eq!(explained[2].tiebreak, Some(1));
+        assert!(explained.iter().all(|e| e.substitute.is_none()));
+    }
+
+    #[test]
+    fn all_wild() {
+        assert_eq!(Jokers.substitute(&[Card(b'J'); 5]), Some(Card(b'J')));
+        assert_eq!(Jokers.hand_type(&[Card(b'J'); 5]), FiveOfAKind);
+    }
+
+    #[test]
+    fn custom_ruleset() {
+        // deuces wild, and otherwise aces low
+        struct DeucesWild;
+        impl Ruleset for DeucesWild {
+            fn card_strength(&self, card: Card) -> u8 {
+                match card.label() {
+                    'A' => 1,
+                    _ => Standard.card_strength(card),
+                }
+            }
+
+            fn is_wildcard(&self, card: Card) -> bool {
+                card.label() == '2'
+            }
+        }
+
+        let solution = Solution::new("2222A 1\nAAAA3 10\nKKKK2 100\n32T3K 1000");
+        assert_eq!(
+            solution
+                .rank(&DeucesWild)
+                .iter()
+                .map(|r| (r.hand.bet(), r.hand_type))
+                .collect_vec(),
+            vec![
+                (1000, ThreeOfAKind),
+                (10, FourOfAKind),
+                (1, FiveOfAKind),
+                (100, FiveOfAKind),
+            ]
+        );
+    }
+
     #[test]
     fn personal_input() {
         let solution = Solution::new(INPUT);
@@ -1,191 +1,424 @@
-use prelude::*;
-
-use num_integer::lcm;
+use std::fmt::Write;
 
-struct Node {
-    left: String,
-    right: String,
-}
+use petgraph::graph::{DiGraph, EdgeIndex, NodeIndex};
+use prelude::*;
 
 pub struct Solution {
-    directions: String,
-    map: HashMap<String, Node>,
+    /// 0 for left and 1 for right, so that they can index into `edges`.
+    directions: Vec<usize>,
+    /// Nodes are numbered in the order they appear in the input.
+    names: Vec<String>,
+    indices: HashMap<String, usize>,
+    /// The left and right neighbors of each node.
+    edges: Vec<[usize; 2]>,
+    ends: Vec<bool>,
 }
 
 impl Day for Solution {
     fn new(input: &str) -> Solution {
         let mut lines = input.lines();
 
-        let directions = lines.next().unwrap().to_owned();
+        let directions = lines
+            .next()
+            .unwrap()
+            .bytes()
+            .map(|direction| match direction {
+                b'L' => 0,
+                b'R' => 1,
+                x => panic!("unexpected direction {:?}", x as char),
+            })
+            .collect();
         lines.next().unwrap();
 
-        let mut map = HashMap::new();
-        for line in lines {
-            let (from, rest) = line.split_once(" = ").unwrap();
-            let (left, right) = rest.split_once(", ").unwrap();
-            map.insert(
-                from.to_owned(),
-                Node {
-                    left: left[1..].to_owned(),
-                    right: right[..3].to_owned(),
-                },
-            );
-        }
+        let nodes = lines
+            .map(|line| {
+                let (from, rest) = line.split_once(" = ").unwrap();
+                let (left, right) = rest.split_once(", ").unwrap();
+                (from, &left[1..], right.trim_end_matches(')'))
+            })
+            .collect_vec();
+
+        let names = nodes
+            .iter()
+            .map(|&(from, ..)| from.to_owned())
+            .collect_vec();
+        let indices: HashMap<String, usize> = names
+            .iter()
+            .enumerate()
+            .map(|(i, name)| (name.clone(), i))
+            .collect();
+        let index = |name: &str| {
+            *indices
+                .get(name)
+                .unwrap_or_else(|| panic!("missing node {name:?}"))
+        };
+        let edges = nodes
+            .iter()
+            .map(|&(_, left, right)| [index(left), index(right)])
+            .collect();
+        let ends = names.iter().map(|name| name.ends_with('Z')).collect();
 
-        Self { directions, map }
+        Self {
+            directions,
+            names,
+            indices,
+            edges,
+            ends,
+        }
     }
 
     fn part1(&self) -> anyhow::Result<u64> {
-        let mut current = "AAA";
-        for (i, direction) in self.directions.bytes().cycle().enumerate() {
-            log::debug!("at {:?}, step {}, direction {}", current, i, direction);
-            if current == "ZZZ" {
-                return Ok(i as u64);
-            }
+        let index = |name| {
+            self.indices
+                .get(name)
+                .copied()
+                .ok_or_else(|| anyhow::anyhow!("there is no {name} node"))
+        };
+        let start = index("AAA")?;
+        let target = index("ZZZ")?;
 
-            current = match direction {
-                b'L' => &self.map[current].left,
-                b'R' => &self.map[current].right,
-                x => panic!("unexpected direction {:?}", x),
+        for (i, current) in self.walk(start).enumerate() {
+            tracing::trace!(current = self.names[current], step = i);
+            if current == target {
+                return Ok(i as u64);
             }
         }
         unreachable!()
     }
 
     fn part2(&self) -> anyhow::Result<u64> {
-        let starting = self
-            .map
-            .keys()
-            .filter(|name| name.ends_with('A'))
-            .collect_vec();
+        match self.ghost_alignment() {
+            Alignment::At(step) => Ok(step),
+            Alignment::Never(reason) => Err(anyhow::anyhow!(reason)),
+        }
+    }
+}
 
-        #[derive(Debug)]
-        struct Data {
-            steps_to_cycle_start: usize,
-            cycle_length: usize,
-            steps_to_end: Vec<usize>,
+/// How one ghost's walk behaves: it lands on `Z` nodes a few times before
+/// settling into a cycle, and then lands on the same `Z` nodes once per cycle
+/// forever after.
+#[derive(Clone, Debug, PartialEq)]
+pub struct GhostCycle {
+    pub start: String,
+    /// The first step that is part of the cycle.
+    pub cycle_start: u64,
+    pub cycle_length: u64,
+    /// Steps before `cycle_start` that are on a `Z` node.
+    pub pre_cycle_ends: Vec<u64>,
+    /// Steps in `cycle_start..cycle_start + cycle_length` that are on a `Z`
+    /// node; each of these recurs every `cycle_length` steps.
+    pub cycle_ends: Vec<u64>,
+}
+
+impl GhostCycle {
+    pub fn is_end(&self, step: u64) -> bool {
+        if step < self.cycle_start {
+            self.pre_cycle_ends.contains(&step)
+        } else {
+            let offset = (step - self.cycle_start) % self.cycle_length;
+            self.cycle_ends.contains(&(self.cycle_start + offset))
         }
-        let mut data = HashMap::new();
+    }
+}
 
+#[derive(Debug, PartialEq)]
+pub enum Alignment {
+    /// Every ghost is on a `Z` node after this many steps, and never before.
+    At(u64),
+    /// The ghosts are never all on `Z` nodes at once, for the reason given.
+    Never(String),
+}
+
+impl Alignment {
+    pub fn step(&self) -> Option<u64> {
+        match self {
+            Alignment::At(step) => Some(*step),
+            Alignment::Never(_) => None,
+        }
+    }
+}
+
+impl Solution {
+    /// The node a ghost is on at each step, starting from `start`.
+    fn walk(&self, start: usize) -> impl Iterator<Item = usize> + '_ {
+        self.directions
+            .iter()
+            .cycle()
+            .scan(start, |current, &direction| {
+                let here = *current;
+                *current = self.edges[here][direction];
+                Some(here)
+            })
+    }
+
+    /// Walk each ghost from its `A` node until it repeats itself.
+    pub fn ghost_cycles(&self) -> Vec<GhostCycle> {
+        let mut starting = (0..self.names.len())
+            .filter(|&i| self.names[i].ends_with('A'))
+            .collect_vec();
+        starting.sort_by_key(|&i| &self.names[i]);
+
+        let len = self.directions.len();
+        let mut res = vec![];
         for s in starting {
-            log::debug!("working on {s:?}");
-            let mut steps_to_end = vec![];
-
-            let mut current = s;
-            let mut seen = HashMap::new();
-            for (i, direction) in self.directions.bytes().cycle().enumerate() {
-                log::debug!("visiting {current:?}");
-                if current.ends_with('Z') {
-                    steps_to_end.push(i);
-                }
+            tracing::debug!(start = self.names[s], "walking ghost");
 
-                // because not only do we have to be at the same node, but we
-                // have to be at the same node *executing the same steps* for it
-                // to count as a cycle.
-                let absolute_step = i % self.directions.len();
-                if let Some(&steps_to_cycle_start) = seen.get(&(current, absolute_step)) {
-                    log::debug!("found cycle starting at {steps_to_cycle_start}, ending at {i}");
-                    data.insert(
-                        s,
-                        Data {
-                            steps_to_cycle_start,
-                            cycle_length: i - steps_to_cycle_start,
-                            steps_to_end,
-                        },
-                    );
-                    break;
-                }
-                seen.insert((current, absolute_step), i);
+            // not only do we have to be at the same node, but we have to be at
+            // the same node *executing the same steps* for it to count as a
+            // cycle.
+            let next =
+                |&(node, i): &(usize, usize)| (self.edges[node][self.directions[i]], (i + 1) % len);
+            let (cycle_start, cycle_length) = cycle::brent((s, 0), next);
+            tracing::debug!(cycle_start, end = cycle_start + cycle_length, "found cycle");
 
-                current = match direction {
-                    b'L' => &self.map[current].left,
-                    b'R' => &self.map[current].right,
-                    x => panic!("unexpected direction {:?}", x),
-                }
-            }
+            let steps_to_end = self
+                .walk(s)
+                .take((cycle_start + cycle_length) as usize)
+                .inspect(|&node| tracing::trace!(current = self.names[node], "visiting"))
+                .positions(|node| self.ends[node])
+                .map(|i| i as u64);
+            let (pre_cycle_ends, cycle_ends) = steps_to_end.partition(|&end| end < cycle_start);
+            res.push(GhostCycle {
+                start: self.names[s].clone(),
+                cycle_start,
+                cycle_length,
+                pre_cycle_ends,
+                cycle_ends,
+            });
         }
 
-        log::info!("collected data: {:?}", data);
+        res
+    }
+
+    /// Find the first step where every ghost is on a `Z` node at once.
+    ///
+    /// This makes no assumptions about the shape of the input: ghosts may
+    /// wander for a while before entering their cycles, may pass through
+    /// several `Z` nodes per cycle, and the cycle lengths may share factors.
+    pub fn ghost_alignment(&self) -> Alignment {
+        let cycles = self.ghost_cycles();
+        tracing::info!(?cycles, "collected data");
+
+        if cycles.is_empty() {
+            return Alignment::Never("there are no nodes ending in A".to_owned());
+        }
+        if let Some(c) = cycles
+            .iter()
+            .find(|c| c.pre_cycle_ends.is_empty() && c.cycle_ends.is_empty())
+        {
+            return Alignment::Never(format!(
+                "the ghost starting at {:?} never reaches a node ending in Z",
+                c.start
+            ));
+        }
+
+        // Before every ghost has entered its cycle, there are few enough steps
+        // to just try all of the ones that work for the first ghost.
+        let all_cycling = cycles.iter().map(|c| c.cycle_start).max().unwrap();
+        let first = &cycles[0];
+        let early = first
+            .pre_cycle_ends
+            .iter()
+            .cloned()
+            .chain(
+                first
+                    .cycle_ends
+                    .iter()
+                    .flat_map(|&end| (end..all_cycling).step_by(first.cycle_length as usize)),
+            )
+            .filter(|&step| step < all_cycling && cycles.iter().all(|c| c.is_end(step)))
+            .min();
+        if let Some(step) = early {
+            return Alignment::At(step);
+        }
 
-        // Assuming that we'll end up finding something well beyond every
-        // starting position's cycle, we're looking for a position that satifies
+        // After that, every ghost needs to be at one of its in-cycle Z nodes,
+        // so each combination of those is a system of congruences
         //
-        //   x = steps_to_end (mod cycle_length)
+        //   x = cycle_end (mod cycle_length)
         //
-        // note that steps_to_cycle_start is not in that equation, since
-        // steps_to_end started at *the beginning* rather than the beginning of
-        // the cycle.  It will simply be used to verify that we calculated a
-        // sufficiently large step count.
-        let remainders = data
-            .values()
-            .map(|d| {
-                d.steps_to_end
+        // that may or may not have a solution.
+        let mut overflowed = false;
+        let best = cycles
+            .iter()
+            .map(|c| {
+                c.cycle_ends
                     .iter()
-                    .map(|&i| i % d.cycle_length)
+                    .map(|&end| (end % c.cycle_length, c.cycle_length))
                     .collect_vec()
             })
-            .multi_cartesian_product();
-
-        let target_cycle = data
-            .values()
-            .map(|d| d.cycle_length as u64)
-            .reduce(lcm)
-            .unwrap();
-
-        let res = remainders
-            .filter_map(|steps_to_end| {
-                chinese_remainder_theorem(
-                    &steps_to_end,
-                    &data.values().map(|d| d.cycle_length).collect_vec(),
-                )
-            })
-            // and make sure the number we come up with is all beyond the cycle-start
-            .map(|res| {
-                let mut res = res as u64;
-                while data.values().any(|d| res < d.steps_to_cycle_start as u64) {
-                    res += target_cycle;
-                }
-                res
+            .multi_cartesian_product()
+            .filter_map(|congruences| {
+                let (residue, modulus) = match math::crt(congruences) {
+                    Ok(solution) => solution,
+                    Err(math::CrtError::Inconsistent { .. }) => return None,
+                    Err(math::CrtError::Overflow) => {
+                        overflowed = true;
+                        return None;
+                    }
+                };
+
+                // and make sure the number we come up with is beyond every
+                // ghost's cycle-start
+                let step = if residue >= all_cycling {
+                    Some(residue)
+                } else {
+                    (all_cycling - residue)
+                        .div_ceil(modulus)
+                        .checked_mul(modulus)
+                        .and_then(|x| x.checked_add(residue))
+                };
+
+                overflowed |= step.is_none();
+                step
             })
-            .min()
-            .unwrap();
+            .min();
 
-        Ok(res)
+        match best {
+            Some(step) => Alignment::At(step),
+            None if overflowed => Alignment::Never(
+                "the ghosts only line up after more than u64::MAX steps".to_owned(),
+            ),
+            None if cycles.iter().any(|c| c.cycle_ends.is_empty()) => Alignment::Never(
+                "a ghost only passes through Z nodes before it starts cycling, and the others \
+                 are never on Z nodes at those steps"
+                    .to_owned(),
+            ),
+            None => Alignment::Never(
+                "no combination of the ghosts' Z nodes is consistent with their cycle lengths"
+                    .to_owned(),
+            ),
+        }
     }
 }
 
-// Implement the CRT with the "sieving" mechanism described on Wikipedia:
-// https://en.wikipedia.org/wiki/Chinese_remainder_theorem#Search_by_sieving
-fn chinese_remainder_theorem(rem: &[usize], moduli: &[usize]) -> Option<usize> {
-    dbg!((rem, moduli));
-    // the result won't be larger than m1 * m2 * ..., and if we get that high we have no result.
-    let limit = moduli.iter().cloned().reduce(lcm).unwrap();
-    assert_eq!(rem.len(), moduli.len());
-
-    let mut current = rem[0];
-    let mut step = moduli[0];
-    for i in 1..rem.len() {
-        dbg!((current, step));
-        // while strictly not necessary, this is called in a context where we might have taken a
-        // while before we entered into a cycle.
-        let target = rem[i] % moduli[i];
-
-        while current % moduli[i] != target {
-            dbg!(current);
-            current += step;
-            if current > limit {
-                return None;
+/// A node of the network, as exported by [`Solution::graph`].
+#[derive(Clone, Debug, PartialEq)]
+pub struct GraphNode {
+    pub name: String,
+    /// Whether a ghost starts here, i.e. the name ends in `A`.
+    pub start: bool,
+    /// Whether the name ends in `Z`.
+    pub end: bool,
+    /// The starting nodes of the ghosts whose cycles pass through here.
+    pub cycles: Vec<String>,
+}
+
+/// An edge of the network, as exported by [`Solution::graph`].
+#[derive(Clone, Debug, PartialEq)]
+pub struct GraphEdge {
+    /// `'L'` or `'R'`.
+    pub direction: char,
+    /// The starting nodes of the ghosts whose cycles take this edge.
+    pub cycles: Vec<String>,
+}
+
+impl Solution {
+    /// The network as a graph with an edge for each of a node's left and right
+    /// neighbors, annotated with each ghost's cycle.
+    pub fn graph(&self) -> DiGraph<GraphNode, GraphEdge> {
+        let mut graph = DiGraph::new();
+
+        // nodes and edges are added in order, so node i is NodeIndex i and its
+        // edge in direction d is EdgeIndex 2 * i + d
+        for (i, name) in self.names.iter().enumerate() {
+            graph.add_node(GraphNode {
+                name: name.clone(),
+                start: name.ends_with('A'),
+                end: self.ends[i],
+                cycles: vec![],
+            });
+        }
+        for (i, neighbors) in self.edges.iter().enumerate() {
+            for (direction, &to) in ['L', 'R'].into_iter().zip(neighbors) {
+                graph.add_edge(
+                    NodeIndex::new(i),
+                    NodeIndex::new(to),
+                    GraphEdge {
+                        direction,
+                        cycles: vec![],
+                    },
+                );
             }
         }
 
-        step = lcm(step, moduli[i]);
+        for c in self.ghost_cycles() {
+            let start = self.indices[&c.start];
+            let steps = self.walk(start).zip(self.directions.iter().cycle());
+            for (current, &direction) in steps
+                .skip(c.cycle_start as usize)
+                .take(c.cycle_length as usize)
+            {
+                let node = &mut graph[NodeIndex::new(current)].cycles;
+                if !node.contains(&c.start) {
+                    node.push(c.start.clone());
+                }
+                let edge = &mut graph[EdgeIndex::new(2 * current + direction)].cycles;
+                if !edge.contains(&c.start) {
+                    edge.push(c.start.clone());
+                }
+            }
+        }
+
+        graph
     }
 
-    Some(current)
+    /// The network in Graphviz DOT format.  Starting nodes are green, `Z`
+    /// nodes are red double circles, and each ghost's cycle is drawn in its own
+    /// color.
+    pub fn to_dot(&self) -> String {
+        const COLORS: [&str; 6] = ["blue", "orange", "purple", "teal", "brown", "magenta"];
+
+        let graph = self.graph();
+        let starts = graph
+            .node_weights()
+            .filter(|n| n.start)
+            .map(|n| n.name.as_str())
+            .collect_vec();
+        let color =
+            |start: &str| COLORS[starts.iter().position(|&s| s == start).unwrap() % COLORS.len()];
+
+        let mut res = "digraph network {\n".to_owned();
+        for node in graph.node_weights() {
+            let mut attrs = vec![];
+            if node.start {
+                attrs.push("style=filled, fillcolor=lightgreen".to_owned());
+            }
+            if node.end {
+                attrs.push("style=filled, fillcolor=salmon, shape=doublecircle".to_owned());
+            }
+            if let [start, ..] = &node.cycles[..] {
+                attrs.push(format!("color={}, penwidth=2", color(start)));
+            }
+            let _ = writeln!(res, "    {:?} [{}];", node.name, attrs.join(", "));
+        }
+
+        for edge in graph.edge_indices() {
+            let (from, to) = graph.edge_endpoints(edge).unwrap();
+            let weight = &graph[edge];
+            let mut attrs = vec![format!("label={}", weight.direction)];
+            if !weight.cycles.is_empty() {
+                let colors = weight.cycles.iter().map(|start| color(start)).join(":");
+                attrs.push(format!("color=\"{colors}\", penwidth=2"));
+            }
+            let _ = writeln!(
+                res,
+                "    {:?} -> {:?} [{}];",
+                graph[from].name,
+                graph[to].name,
+                attrs.join(", ")
+            );
+        }
+        res += "}\n";
+
+        res
+    }
 }
 
 #[cfg(test)]
 mod test {
+    use proptest::prelude::*;
+
     use super::*;
 
     #[test]
@@ -194,6 +427,165 @@ mod test {
         assert_eq!(Solution::new(EXAMPLE_2).part2().unwrap(), 6);
     }
 
+    #[test]
+    fn example_cycles() {
+        let cycles = Solution::new(EXAMPLE_2).ghost_cycles();
+        assert_eq!(
+            cycles,
+            vec![
+                GhostCycle {
+                    start: "11A".to_owned(),
+                    cycle_start: 1,
+                    cycle_length: 2,
+                    pre_cycle_ends: vec![],
+                    cycle_ends: vec![2],
+                },
+                GhostCycle {
+                    start: "22A".to_owned(),
+                    cycle_start: 1,
+                    cycle_length: 6,
+                    pre_cycle_ends: vec![],
+                    cycle_ends: vec![3, 6],
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn example_graph() {
+        let solution = Solution::new(EXAMPLE_2);
+        let graph = solution.graph();
+        assert_eq!(graph.node_count(), 8);
+        assert_eq!(graph.edge_count(), 16);
+
+        let node = |name: &str| graph.node_weights().find(|n| n.name == name).unwrap();
+        assert!(node("11A").start && !node("11A").end);
+        assert!(node("22Z").end);
+        assert!(node("11A").cycles.is_empty());
+        assert_eq!(node("11B").cycles, vec!["11A"]);
+        assert_eq!(node("22Z").cycles, vec!["22A"]);
+        assert!(node("XXX").cycles.is_empty());
+
+        // 11A's cycle is 11B -R-> 11Z -L-> 11B
+        let cycle_edges = graph
+            .edge_indices()
+            .filter(|&e| graph[e].cycles == ["11A"])
+            .map(|e| {
+                let (from, to) = graph.edge_endpoints(e).unwrap();
+                (
+                    graph[from].name.as_str(),
+                    graph[e].direction,
+                    graph[to].name.as_str(),
+                )
+            })
+            .sorted()
+            .collect_vec();
+        assert_eq!(cycle_edges, vec![("11B", 'R', "11Z"), ("11Z", 'L', "11B")]);
+
+        let dot = solution.to_dot();
+        assert!(dot.starts_with("digraph network {\n"));
+        assert!(dot.contains("    \"11A\" [style=filled, fillcolor=lightgreen];\n"));
+        assert!(dot.contains("    \"11B\" -> \"11Z\" [label=R, color=\"blue\", penwidth=2];\n"));
+        assert!(dot.contains("    \"11B\" -> \"XXX\" [label=L];\n"));
+    }
+
+    #[test]
+    fn never_aligns() {
+        // 11A is on a Z node at every odd step, and 22A at every even one
+        let solution = Solution::new(
+            "L
+
+11A = (11Z, 11Z)
+11Z = (11B, 11B)
+11B = (11Z, 11Z)
+22A = (22B, 22B)
+22B = (22Z, 22Z)
+22Z = (22B, 22B)",
+        );
+        assert_eq!(solution.ghost_alignment().step(), None);
+        assert!(solution.part2().is_err());
+
+        // and this one never gets anywhere at all
+        let solution = Solution::new(
+            "L
+
+11A = (11B, 11B)
+11B = (11A, 11A)",
+        );
+        assert_eq!(
+            solution.ghost_alignment(),
+            Alignment::Never(
+                "the ghost starting at \"11A\" never reaches a node ending in Z".to_owned()
+            )
+        );
+    }
+
+    /// Build an input from a list of (suffix, left, right) nodes.
+    fn graph_input(directions: &str, nodes: &[(char, usize, usize)]) -> String {
+        let name = |i: usize| format!("{i:02}{}", nodes[i].0);
+        let mut input = format!("{directions}\n\n");
+        for (i, &(_, left, right)) in nodes.iter().enumerate() {
+            input += &format!("{} = ({}, {})\n", name(i), name(left), name(right));
+        }
+        input
+    }
+
+    fn brute_force(solution: &Solution, limit: u64) -> Option<u64> {
+        let mut current = (0..solution.names.len())
+            .filter(|&i| solution.names[i].ends_with('A'))
+            .collect_vec();
+        if current.is_empty() {
+            return None;
+        }
+
+        for (i, &direction) in solution.directions.iter().cycle().enumerate() {
+            if i as u64 > limit {
+                return None;
+            }
+            if current.iter().all(|&node| solution.ends[node]) {
+                return Some(i as u64);
+            }
+            for node in current.iter_mut() {
+                *node = solution.edges[*node][direction];
+            }
+        }
+        unreachable!()
+    }
+
+    proptest! {
+        #[test]
+        fn matches_brute_force(
+            directions in "[LR]{1,3}",
+            nodes in prop::collection::vec((prop::sample::select(vec!['A', 'B', 'Z']), 0..4usize, 0..4usize), 4),
+        ) {
+            let solution = Solution::new(&graph_input(&directions, &nodes));
+            // each ghost has at most 12 states, so they have all been cycling
+            // by step 12 and the whole system repeats every lcm(cycle lengths)
+            // after that, which is at most lcm(7, 8, 9, 11) = 5544.
+            prop_assert_eq!(solution.ghost_alignment().step(), brute_force(&solution, 12 + 5544));
+        }
+    }
+
+    #[test]
+    fn long_walk() {
+        // every node loops back to itself on L, so it takes a thousand steps to
+        // move one node along the ring
+        let n = 1000;
+        let name = |i: usize| match i {
+            0 => "AAA".to_owned(),
+            i if i == n - 1 => "ZZZ".to_owned(),
+            i => format!("{i:03}"),
+        };
+        let mut input = format!("{}R\n\n", "L".repeat(n - 1));
+        for i in 0..n {
+            input += &format!("{} = ({}, {})\n", name(i), name(i), name((i + 1) % n));
+        }
+
+        let solution = Solution::new(&input);
+        assert_eq!(solution.part1().unwrap(), 999_000);
+        assert_eq!(solution.part2().unwrap(), 999_000);
+    }
+
     #[test]
     fn personal_input() {
         let solution = Solution::new(INPUT);
@@ -1,11 +1,13 @@
+use std::fmt;
+
 use prelude::*;
 
 pub struct Solution(Vec<Vec<i64>>);
 
-fn calculate_diffs(values: &[i64]) -> Vec<Vec<i64>> {
-    let mut diffs: Vec<Vec<i64>> = vec![values.to_vec()];
+fn calculate_diffs(values: &[i64]) -> Result<Vec<Vec<i128>>, NotPolynomial> {
+    let mut diffs: Vec<Vec<i128>> = vec![values.iter().map(|&v| v as i128).collect()];
 
-    log::debug!("Calculating interpolated value for {values:?}");
+    tracing::debug!(?values, "calculating interpolated value");
 
     while diffs.last().unwrap().iter().any(|&i| i != 0) {
         let next_diff = diffs
@@ -13,29 +15,241 @@ fn calculate_diffs(values: &[i64]) -> Vec<Vec<i64>> {
             .unwrap()
             .iter()
             .tuple_windows()
-            .map(|(a, b)| b - a)
-            .collect_vec();
+            .map(|(&a, &b)| b.checked_sub(a))
+            .collect::<Option<Vec<_>>>();
+        let Some(next_diff) = next_diff else {
+            // the differences can double at every step, so they've grown
+            // past anything a polynomial over i64 values could do
+            let best_fit_degree = best_fit_degree(&diffs);
+            tracing::debug!(?best_fit_degree, "differences overflowed");
+            return Err(NotPolynomial { best_fit_degree });
+        };
         diffs.push(next_diff);
     }
 
-    log::debug!("found diffs {diffs:?}");
-    diffs
+    tracing::trace!(?diffs, "found diffs");
+
+    // running out of values isn't the same as the differences reaching zero
+    if diffs.last().unwrap().is_empty() {
+        let best_fit_degree = best_fit_degree(&diffs);
+        tracing::debug!(?best_fit_degree, "differences never settled");
+        return Err(NotPolynomial { best_fit_degree });
+    }
+
+    Ok(diffs)
 }
 
-fn next_value(values: &[i64]) -> i64 {
-    let diffs = calculate_diffs(values);
-    let res = diffs.iter().map(|d| d.last().unwrap()).sum::<i64>();
-    log::debug!("returning {res:?}");
-    res
+/// The degree whose next row of differences is smallest compared to the
+/// values themselves, if it is much smaller.  Noise grows as it's differenced,
+/// so the row just past a noisy polynomial's degree is where it bottoms out,
+/// while an exponential's differences never get any smaller.
+fn best_fit_degree(diffs: &[Vec<i128>]) -> Option<usize> {
+    let mean_magnitude =
+        |row: &[i128]| row.iter().map(|v| v.unsigned_abs() as f64).sum::<f64>() / row.len() as f64;
+
+    // a row with a single value always fits exactly, so it says nothing
+    let (degree, ratio) = diffs
+        .iter()
+        .enumerate()
+        .skip(1)
+        .take_while(|(_, row)| row.len() >= 2)
+        .map(|(i, row)| {
+            let ratio = mean_magnitude(row) / mean_magnitude(&diffs[0][..row.len()]);
+            (i - 1, ratio)
+        })
+        .min_by(|a, b| a.1.total_cmp(&b.1))?;
+
+    (ratio < 0.1).then_some(degree)
+}
+
+/// A history whose differences run out before they reach all zeros.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct NotPolynomial {
+    /// The degree of polynomial that comes closest, if any comes close at all.
+    pub best_fit_degree: Option<usize>,
+}
+
+impl fmt::Display for NotPolynomial {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "the differences never settle to zero")?;
+        if let Some(degree) = self.best_fit_degree {
+            write!(f, "; the closest fit is degree {degree}")?;
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for NotPolynomial {}
+
+/// A fraction in lowest terms, with a positive denominator.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Fraction {
+    numer: i128,
+    denom: i128,
+}
+
+impl Fraction {
+    pub fn new(numer: i128, denom: i128) -> Fraction {
+        assert!(denom != 0, "fraction with a zero denominator");
+        let (g, _, _) = math::extended_gcd(numer, denom);
+        let g = g * denom.signum();
+        Fraction {
+            numer: numer / g,
+            denom: denom / g,
+        }
+    }
+
+    pub fn numer(&self) -> i128 {
+        self.numer
+    }
+
+    pub fn denom(&self) -> i128 {
+        self.denom
+    }
+
+    fn checked_add(self, other: Fraction) -> Option<Fraction> {
+        let (g, _, _) = math::extended_gcd(self.denom, other.denom);
+        let numer = self
+            .numer
+            .checked_mul(other.denom / g)?
+            .checked_add(other.numer.checked_mul(self.denom / g)?)?;
+        let denom = self.denom.checked_mul(other.denom / g)?;
+        Some(Fraction::new(numer, denom))
+    }
+}
+
+impl From<i128> for Fraction {
+    fn from(value: i128) -> Fraction {
+        Fraction {
+            numer: value,
+            denom: 1,
+        }
+    }
+}
+
+impl fmt::Display for Fraction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.denom == 1 {
+            write!(f, "{}", self.numer)
+        } else {
+            write!(f, "{}/{}", self.numer, self.denom)
+        }
+    }
+}
+
+/// The lowest-degree polynomial through a history, taking its first value to
+/// be at x = 0.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Polynomial {
+    /// The first value of each row of differences, down to the last row that
+    /// isn't all zeros, so that f(x) is the sum of `newton[k] * (x choose k)`.
+    newton: Vec<i128>,
+    len: usize,
+}
+
+impl Polynomial {
+    /// Fails unless the history has enough values for the differences to
+    /// reach a row of zeros.
+    pub fn fit(values: &[i64]) -> Result<Polynomial, NotPolynomial> {
+        let diffs = calculate_diffs(values)?;
+        let newton = diffs[..diffs.len() - 1].iter().map(|d| d[0]).collect();
+        Ok(Polynomial {
+            newton,
+            len: values.len(),
+        })
+    }
+
+    /// The zero polynomial counts as degree 0.
+    pub fn degree(&self) -> usize {
+        self.newton.len().saturating_sub(1)
+    }
+
+    /// The Newton forward-difference coefficients, lowest order first.
+    pub fn newton_coefficients(&self) -> &[i128] {
+        &self.newton
+    }
+
+    /// The coefficients of each power of x, lowest power first, or `None` if
+    /// they don't fit in an `i128`.
+    pub fn coefficients(&self) -> Option<Vec<Fraction>> {
+        let mut res = vec![Fraction::from(0); self.newton.len()];
+        // x (x - 1) ... (x - k + 1), lowest power first
+        let mut falling = vec![1i128];
+        let mut factorial = 1i128;
+
+        for (k, &d) in self.newton.iter().enumerate() {
+            if k > 0 {
+                let root = k as i128 - 1;
+                let mut next = vec![0i128; falling.len() + 1];
+                for (i, &c) in falling.iter().enumerate() {
+                    next[i + 1] = next[i + 1].checked_add(c)?;
+                    next[i] = next[i].checked_sub(c.checked_mul(root)?)?;
+                }
+                falling = next;
+                factorial = factorial.checked_mul(k as i128)?;
+            }
+
+            for (i, &c) in falling.iter().enumerate() {
+                res[i] = res[i].checked_add(Fraction::new(d.checked_mul(c)?, factorial))?;
+            }
+        }
+
+        Some(res)
+    }
+
+    /// The value at `x`, or `None` if it doesn't fit in an `i128`.
+    pub fn at(&self, x: i128) -> Option<i128> {
+        let mut binomial = 1i128;
+        let mut res = 0i128;
+        for (k, &d) in self.newton.iter().enumerate() {
+            if k > 0 {
+                let k = k as i128;
+                binomial = binomial.checked_mul(x.checked_sub(k - 1)?)? / k;
+            }
+            res = res.checked_add(d.checked_mul(binomial)?)?;
+        }
+        Some(res)
+    }
+
+    /// The value `k` steps after the last value of the history, or `-k` steps
+    /// before the first one when `k` is negative.
+    pub fn extrapolate(&self, k: i64) -> Option<i128> {
+        let x = if k >= 0 {
+            self.len as i128 - 1 + k as i128
+        } else {
+            k as i128
+        };
+        let res = self.at(x);
+        tracing::debug!(k, ?res, "extrapolated");
+        res
+    }
 }
 
-fn prev_value(values: &[i64]) -> i64 {
-    let diffs = calculate_diffs(values);
-    let mut res = 0; // assume that the last-level diff must be zero.
-    for i in (0..(diffs.len() - 1)).rev() {
-        res = diffs[i][0] - res;
+impl Solution {
+    pub fn histories(&self) -> &[Vec<i64>] {
+        &self.0
+    }
+
+    pub fn polynomials(&self) -> anyhow::Result<Vec<Polynomial>> {
+        self.0
+            .iter()
+            .enumerate()
+            .map(|(i, history)| {
+                Polynomial::fit(history)
+                    .with_context(|| format!("the history on line {} isn't a polynomial", i + 1))
+            })
+            .collect()
+    }
+
+    /// The sum of every history extrapolated `k` steps, as in
+    /// [`Polynomial::extrapolate`].
+    pub fn extrapolate(&self, k: i64) -> anyhow::Result<i128> {
+        self.polynomials()?.iter().try_fold(0i128, |sum, p| {
+            p.extrapolate(k)
+                .and_then(|value| sum.checked_add(value))
+                .with_context(|| format!("extrapolating {k} steps overflows an i128"))
+        })
     }
-    res
 }
 
 impl Day for Solution {
@@ -53,19 +267,11 @@ impl Day for Solution {
     }
 
     fn part1(&self) -> anyhow::Result<u64> {
-        Ok(self
-            .0
-            .iter()
-            .map(|history| next_value(history))
-            .sum::<i64>() as u64)
+        Ok(self.extrapolate(1)? as u64)
     }
 
     fn part2(&self) -> anyhow::Result<u64> {
-        Ok(self
-            .0
-            .iter()
-            .map(|history: &Vec<i64>| prev_value(history))
-            .sum::<i64>() as u64)
+        Ok(self.extrapolate(-1)? as u64)
     }
 }
 
@@ -80,6 +286,100 @@ mod test {
         assert_eq!(solution.part2().unwrap(), 2);
     }
 
+    #[test]
+    fn polynomial() {
+        let p = Polynomial::fit(&[1, 3, 6, 10, 15, 21]).unwrap();
+        assert_eq!(p.degree(), 2);
+        assert_eq!(p.newton_coefficients(), &[1, 2, 1]);
+        assert_eq!(
+            p.coefficients().unwrap(),
+            vec![Fraction::from(1), Fraction::new(3, 2), Fraction::new(1, 2)]
+        );
+        assert_eq!(p.coefficients().unwrap()[1].to_string(), "3/2");
+        assert_eq!(p.extrapolate(1), Some(28));
+        assert_eq!(p.extrapolate(2), Some(36));
+        assert_eq!(p.extrapolate(-1), Some(0));
+        assert_eq!(p.extrapolate(-3), Some(1));
+
+        let line = Polynomial::fit(&[0, 3, 6, 9]).unwrap();
+        assert_eq!(line.degree(), 1);
+        assert_eq!(line.extrapolate(i64::MAX), Some(3 * (i64::MAX as i128 + 3)));
+
+        assert_eq!(Polynomial::fit(&[0, 0, 0]).unwrap().degree(), 0);
+        assert_eq!(Polynomial::fit(&[0, 0, 0]).unwrap().extrapolate(5), Some(0));
+    }
+
+    #[test]
+    fn coefficients_match_values() {
+        let solution = Solution::new(INPUT);
+        for (history, p) in solution
+            .histories()
+            .iter()
+            .zip(solution.polynomials().unwrap())
+        {
+            let coefficients = p.coefficients().unwrap();
+            for (x, &value) in history.iter().enumerate() {
+                // evaluate with a common denominator to stay exact
+                let mut numer = 0i128;
+                let denom = coefficients
+                    .iter()
+                    .fold(1, |l, c| l / math::extended_gcd(l, c.denom()).0 * c.denom());
+                for (i, c) in coefficients.iter().enumerate() {
+                    numer += c.numer() * (denom / c.denom()) * (x as i128).pow(i as u32);
+                }
+                assert_eq!(numer, value as i128 * denom);
+            }
+        }
+    }
+
+    #[test]
+    fn not_polynomial() {
+        let doubling = (0..10).map(|i| 1 << i).collect_vec();
+        assert_eq!(
+            Polynomial::fit(&doubling),
+            Err(NotPolynomial {
+                best_fit_degree: None
+            })
+        );
+
+        // a quadratic with a little noise on top
+        let noisy = (0..12)
+            .map(|i: i64| 1000 * i * i + [0, 1, -1][i as usize % 3])
+            .collect_vec();
+        assert_eq!(
+            Polynomial::fit(&noisy),
+            Err(NotPolynomial {
+                best_fit_degree: Some(2)
+            })
+        );
+
+        // differences that double at every step overflow even an i128
+        let alternating = (0..200)
+            .map(|i| if i % 2 == 0 { i64::MIN } else { i64::MAX })
+            .collect_vec();
+        assert_eq!(
+            Polynomial::fit(&alternating),
+            Err(NotPolynomial {
+                best_fit_degree: None
+            })
+        );
+
+        // two values aren't enough to tell that a line is a line
+        assert!(Polynomial::fit(&[1, 2]).is_err());
+        assert!(Polynomial::fit(&[1, 2, 3]).is_ok());
+
+        let solution = Solution::new("0 3 6 9 12 15\n1 2 4 8 16 32\n");
+        let error = solution.part1().unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "the history on line 2 isn't a polynomial"
+        );
+        assert_eq!(
+            error.root_cause().to_string(),
+            "the differences never settle to zero"
+        );
+    }
+
     #[test]
     fn personal_input() {
         let solution = Solution::new(INPUT);
@@ -2,8 +2,8 @@ use std::{cmp::min, collections::VecDeque};
 
 use prelude::*;
 
-#[derive(Debug, PartialEq)]
-enum Tile {
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Tile {
     Ground,
     NE,
     SE,
@@ -17,9 +17,37 @@ enum Tile {
 use Tile::*;
 
 impl Tile {
+    /// The character the tile is written as in the puzzle input.
+    pub fn symbol(&self) -> char {
+        match self {
+            Ground => '.',
+            NE => 'L',
+            SE => 'F',
+            NW => 'J',
+            SW => '7',
+            Vertical => '|',
+            Horizontal => '-',
+            Starting => 'S',
+        }
+    }
+
+    /// The tile drawn with box-drawing characters.
+    pub fn box_drawing(&self) -> char {
+        match self {
+            Ground => '·',
+            NE => '└',
+            SE => '┌',
+            NW => '┘',
+            SW => '┐',
+            Vertical => '│',
+            Horizontal => '─',
+            Starting => 'S',
+        }
+    }
+
     fn adjacent<'a>(
         &'a self,
-        data: &Vec<Vec<Tile>>,
+        data: &[Vec<Tile>],
         x: usize,
         y: usize,
     ) -> impl Iterator<Item = (usize, usize)> + 'a {
@@ -40,7 +68,7 @@ impl Tile {
 }
 
 fn adjacent_including_diagonal_tripled_coordinate(
-    data: &Vec<Vec<Tile>>,
+    data: &[Vec<Tile>],
     x: usize,
     y: usize,
 ) -> impl Iterator<Item = (usize, usize)> {
@@ -89,80 +117,270 @@ impl Day for Solution {
     }
 
     fn part2(&self) -> anyhow::Result<u64> {
-        // Treat all of the pipes in the loop as 3x3 grids of
-        //    _X_  _X_  ___
-        //    _XX  _X_  XX_
-        //    ___  _X_  _X_   , etc.
-        // to allow the outside to flood-fill from (0,0).
-        // And treat all pipes *not* in the loop as empty ground.
+        self.enclosed_area()
+    }
+}
 
-        let in_loop = self.get_distances()?;
+/// Where a tile is relative to the loop.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Cell {
+    Loop,
+    /// Enclosed by the loop, whether it's ground or a pipe that isn't part of
+    /// the loop, just like part 2 counts it.
+    Inside,
+    Outside,
+    /// A pipe outside the loop that isn't part of it.
+    Unconnected,
+}
 
-        let mut seen = HashSet::new();
-        // to_visit is in the *tripled* coordinate space.
-        let mut to_visit = VecDeque::from(vec![(0, 0)]);
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct RenderOptions {
+    /// Leave out every tile that isn't part of the loop.
+    pub only_loop: bool,
+    /// Draw the tiles enclosed by the loop as `I`.
+    pub mark_interior: bool,
+}
 
-        // reverse-engineer what the starting tile must have been
-        let starting_tile;
-        {
-            let (starting_x, starting_y) = self
-                .0
-                .iter()
-                .enumerate()
-                .find_map(|(i, row)| {
-                    row.iter().enumerate().find_map(|(j, tile)| {
-                        if tile == &Starting {
-                            Some((i, j))
-                        } else {
-                            None
-                        }
-                    })
+fn det(a: (i64, i64), b: (i64, i64)) -> i64 {
+    a.0 * b.1 - a.1 * b.0
+}
+
+impl Solution {
+    fn start(&self) -> anyhow::Result<(usize, usize)> {
+        self.0
+            .iter()
+            .enumerate()
+            .find_map(|(i, row)| {
+                row.iter().enumerate().find_map(|(j, tile)| {
+                    if tile == &Starting {
+                        Some((i, j))
+                    } else {
+                        None
+                    }
                 })
-                .ok_or_else(|| anyhow::anyhow!("there was no starting tile"))?;
+            })
+            .ok_or_else(|| anyhow::anyhow!("there was no starting tile"))
+    }
 
-            let (mut north, mut east, mut south, mut west) = (false, false, false, false);
+    /// The pipes that flow to or from the starting tile.
+    fn start_neighbors(&self, (starting_x, starting_y): (usize, usize)) -> Vec<(usize, usize)> {
+        adjacent_including_diagonal(&self.0, starting_x, starting_y)
+            .filter(|&(x, y)| {
+                // skip diagonals, and only start with the connected pipes, not
+                // ones that make a glancing blow
+                (x == starting_x || y == starting_y)
+                    && self.0[x][y] != Ground
+                    && self.0[x][y]
+                        .adjacent(&self.0, x, y)
+                        .contains(&(starting_x, starting_y))
+            })
+            .collect()
+    }
 
-            for (x, y) in adjacent_including_diagonal(&self.0, starting_x, starting_y) {
-                if x != starting_x && y != starting_y {
-                    // diagonal, so skip
-                    continue;
-                }
+    /// Reverse-engineer what the starting tile must have been.
+    pub fn starting_tile(&self) -> anyhow::Result<Tile> {
+        let (starting_x, starting_y) = self.start()?;
+        let (mut north, mut east, mut south, mut west) = (false, false, false, false);
+        for (x, y) in self.start_neighbors((starting_x, starting_y)) {
+            if x < starting_x {
+                north = true;
+            } else if y > starting_y {
+                east = true;
+            } else if x > starting_x {
+                south = true;
+            } else if y < starting_y {
+                west = true;
+            }
+        }
 
-                if self.0[x][y] == Ground {
-                    // only start with the two connected pipes
-                    continue;
-                }
+        Ok(match (north, east, south, west) {
+            (true, false, true, false) => Vertical,
+            (true, true, false, false) => NE,
+            (true, false, false, true) => NW,
+            (false, true, false, true) => Horizontal,
+            (false, true, true, false) => SE,
+            (false, false, true, true) => SW,
+            _ => anyhow::bail!("there aren't exactly two pipes connected to the starting tile"),
+        })
+    }
 
-                // and only start with pipes that flow to/from S; exclude pipes that make a glancing blow
-                if !self.0[x][y]
-                    .adjacent(&self.0, x, y)
-                    .contains(&(starting_x, starting_y))
-                {
-                    continue;
-                }
+    /// Every tile of the loop through the starting tile, in order, beginning
+    /// with the starting tile itself.
+    pub fn main_loop(&self) -> anyhow::Result<Vec<(usize, usize)>> {
+        let start = self.start()?;
+        let start_neighbors = self.start_neighbors(start);
+        let mut res = vec![start];
+        let mut previous = start;
+        let mut current = *start_neighbors
+            .first()
+            .ok_or_else(|| anyhow::anyhow!("nothing is connected to the starting tile"))?;
+
+        // whether the pipe at `to` leads back to `from`; the starting tile
+        // connects to whatever start_neighbors found
+        let connects = |to: (usize, usize), from: (usize, usize)| {
+            if to == start {
+                start_neighbors.contains(&from)
+            } else if self.0[to.0][to.1] == Ground {
+                false
+            } else {
+                self.0[to.0][to.1]
+                    .adjacent(&self.0, to.0, to.1)
+                    .contains(&from)
+            }
+        };
 
-                if x < starting_x {
-                    north = true;
-                } else if y > starting_y {
-                    east = true;
-                } else if x > starting_x {
-                    south = true;
-                } else if y < starting_y {
-                    west = true;
-                }
+        while current != start {
+            let (x, y) = current;
+            if self.0[x][y] == Ground || res.len() > self.0.len() * self.0[0].len() {
+                anyhow::bail!("the loop is broken at {current:?}");
             }
+            res.push(current);
 
-            starting_tile = match (north, east, south, west) {
-                (true, false, true, false) => Vertical,
-                (true, true, false, false) => NE,
-                (true, false, false, true) => NW,
-                (false, true, false, true) => Horizontal,
-                (false, true, true, false) => SE,
-                (false, false, true, true) => SW,
-                _ => anyhow::bail!("found more than two pipes adjacent to the starting pipe"),
-            };
+            let next = self.0[x][y]
+                .adjacent(&self.0, x, y)
+                .find(|&next| next != previous && connects(next, current))
+                .ok_or_else(|| anyhow::anyhow!("the loop is broken at {current:?}"))?;
+            previous = current;
+            current = next;
         }
 
+        tracing::debug!(length = res.len(), "found the loop");
+        Ok(res)
+    }
+
+    /// The corners of the loop, in order.  Straight pipes lie on the edges
+    /// between them.
+    pub fn polygon(&self) -> anyhow::Result<Vec<(usize, usize)>> {
+        self.polygon_of(&self.main_loop()?)
+    }
+
+    fn polygon_of(&self, main_loop: &[(usize, usize)]) -> anyhow::Result<Vec<(usize, usize)>> {
+        let starting_tile = self.starting_tile()?;
+        Ok(main_loop
+            .iter()
+            .copied()
+            .filter(|&(x, y)| {
+                let tile = match self.0[x][y] {
+                    Starting => starting_tile,
+                    tile => tile,
+                };
+                !matches!(tile, Vertical | Horizontal)
+            })
+            .collect())
+    }
+
+    /// The number of tiles enclosed by the loop.
+    pub fn enclosed_area(&self) -> anyhow::Result<u64> {
+        let main_loop = self.main_loop()?;
+        let vertices = self
+            .polygon_of(&main_loop)?
+            .into_iter()
+            .map(|(x, y)| (x as i64, y as i64))
+            .collect_vec();
+        let boundary = main_loop.len() as i64;
+
+        // Implement the "Shoelace Formula"
+        let two_a = vertices
+            .iter()
+            .chain(vertices.first())
+            .tuple_windows()
+            .map(|(&a, &b)| det(a, b))
+            .sum::<i64>()
+            .abs();
+
+        // Pick's theorem: A = interior + boundary / 2 - 1
+        let interior = (two_a - boundary + 2) / 2;
+        tracing::debug!(two_a, boundary, interior, "shoelace");
+        Ok(interior as u64)
+    }
+
+    /// Classify every tile by casting a ray along its row: it's inside the
+    /// loop if the ray crosses the loop an odd number of times.
+    pub fn classify(&self) -> anyhow::Result<Vec<Vec<Cell>>> {
+        let starting_tile = self.starting_tile()?;
+        let mut in_loop = self
+            .0
+            .iter()
+            .map(|row| vec![false; row.len()])
+            .collect_vec();
+        for (x, y) in self.main_loop()? {
+            in_loop[x][y] = true;
+        }
+
+        Ok(self
+            .0
+            .iter()
+            .zip(in_loop)
+            .map(|(row, in_loop)| {
+                let mut inside = false;
+                row.iter()
+                    .zip(in_loop)
+                    .map(|(&tile, in_loop)| {
+                        let tile = if tile == Starting {
+                            starting_tile
+                        } else {
+                            tile
+                        };
+                        if in_loop {
+                            // only count pipes heading north, so that running
+                            // along a horizontal stretch counts as crossing it
+                            // only if it leaves in the opposite direction
+                            if matches!(tile, Vertical | NE | NW) {
+                                inside = !inside;
+                            }
+                            Cell::Loop
+                        } else if inside {
+                            Cell::Inside
+                        } else if tile == Ground {
+                            Cell::Outside
+                        } else {
+                            Cell::Unconnected
+                        }
+                    })
+                    .collect()
+            })
+            .collect())
+    }
+
+    /// Draw the grid with box-drawing characters, one line per row.
+    pub fn render(&self, options: RenderOptions) -> anyhow::Result<String> {
+        let cells = if options.only_loop || options.mark_interior {
+            Some(self.classify()?)
+        } else {
+            None
+        };
+
+        let mut res = String::new();
+        for (x, row) in self.0.iter().enumerate() {
+            for (y, tile) in row.iter().enumerate() {
+                let cell = cells.as_ref().map(|cells| cells[x][y]);
+                res.push(match cell {
+                    Some(Cell::Inside) if options.mark_interior => 'I',
+                    Some(Cell::Loop) | None => tile.box_drawing(),
+                    _ if options.only_loop => ' ',
+                    _ => tile.box_drawing(),
+                });
+            }
+            res.push('\n');
+        }
+        Ok(res)
+    }
+
+    /// The same as [`Solution::enclosed_area`], but found by flood-filling the
+    /// outside instead, as a cross-check.
+    pub fn enclosed_area_flood_fill(&self) -> anyhow::Result<u64> {
+        // Treat all of the pipes in the loop as 3x3 grids of
+        //    _X_  _X_  ___
+        //    _XX  _X_  XX_
+        //    ___  _X_  _X_   , etc.
+        // to allow the outside to flood-fill from (0,0).
+        // And treat all pipes *not* in the loop as empty ground.
+        let in_loop = self.get_distances()?;
+        let mut seen = HashSet::new();
+        // to_visit is in the *tripled* coordinate space.
+        let mut to_visit = VecDeque::from(vec![(0, 0)]);
+        let starting_tile = self.starting_tile()?;
+
         while let Some((x, y)) = to_visit.pop_front() {
             if !seen.insert((x, y)) {
                 // seen already contained (x, y)
@@ -230,9 +448,7 @@ impl Day for Solution {
 
         Ok(res)
     }
-}
 
-impl Solution {
     pub fn get_distances(&self) -> anyhow::Result<HashMap<(usize, usize), u64>> {
         #[derive(Debug)]
         struct ToVisit {
@@ -243,53 +459,22 @@ impl Solution {
         let mut seen = HashMap::new();
         let mut to_visit = VecDeque::new();
 
-        let (starting_x, starting_y) = self
-            .0
-            .iter()
-            .enumerate()
-            .find_map(|(i, row)| {
-                row.iter().enumerate().find_map(|(j, tile)| {
-                    if tile == &Starting {
-                        Some((i, j))
-                    } else {
-                        None
-                    }
-                })
-            })
-            .ok_or_else(|| anyhow::anyhow!("there was no starting tile"))?;
-        log::debug!("starting at {:?}", (starting_x, starting_y));
+        let (starting_x, starting_y) = self.start()?;
 
+        tracing::debug!(x = starting_x, y = starting_y, "starting tile");
         seen.insert((starting_x, starting_y), 0);
 
-        for (x, y) in adjacent_including_diagonal(&self.0, starting_x, starting_y) {
-            if x != starting_x && y != starting_y {
-                // diagonal, so skip
-                continue;
-            }
-
-            if self.0[x][y] == Ground {
-                // only start with the two connected pipes
-                continue;
-            }
-
-            // and only start with pipes that flow to/from S; exclude pipes that make a glancing blow
-            if !self.0[x][y]
-                .adjacent(&self.0, x, y)
-                .contains(&(starting_x, starting_y))
-            {
-                continue;
-            }
-
-            to_visit.push_back(ToVisit {
-                distance: 1,
-                coord: (x, y),
-            });
+        for coord in self.start_neighbors((starting_x, starting_y)) {
+            to_visit.push_back(ToVisit { distance: 1, coord });
         }
 
         while let Some(i) = to_visit.pop_front() {
-            log::debug!(
-                "visiting {i:?}, which is a {:?}",
-                self.0[i.coord.0][i.coord.1]
+            tracing::trace!(
+                x = i.coord.0,
+                y = i.coord.1,
+                distance = i.distance,
+                tile = ?self.0[i.coord.0][i.coord.1],
+                "visiting"
             );
             if let Some(existing) = seen.get(&i.coord) {
                 if *existing <= i.distance {
@@ -302,7 +487,7 @@ impl Solution {
 
             let (x, y) = i.coord;
             for (next_x, next_y) in self.0[x][y].adjacent(&self.0, x, y) {
-                log::debug!("neighbor is {:?}", (next_x, next_y));
+                tracing::trace!(x = next_x, y = next_y, "neighbor");
                 to_visit.push_back(ToVisit {
                     distance,
                     coord: (next_x, next_y),
@@ -313,3 +498,158 @@ impl Solution {
         Ok(seen)
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn example() {
+        let solution = Solution::new(EXAMPLE_1);
+        assert_eq!(solution.part1().unwrap(), 8);
+        assert_eq!(solution.starting_tile().unwrap(), SE);
+
+        let solution = Solution::new(EXAMPLE_SQUARE);
+        assert_eq!(solution.part1().unwrap(), 4);
+        assert_eq!(solution.part2().unwrap(), 1);
+        assert_eq!(
+            solution.main_loop().unwrap(),
+            vec![
+                (1, 1),
+                (1, 2),
+                (1, 3),
+                (2, 3),
+                (3, 3),
+                (3, 2),
+                (3, 1),
+                (2, 1)
+            ]
+        );
+        assert_eq!(
+            solution.classify().unwrap()[2],
+            vec![
+                Cell::Outside,
+                Cell::Loop,
+                Cell::Inside,
+                Cell::Loop,
+                Cell::Outside
+            ]
+        );
+        assert_eq!(
+            solution.polygon().unwrap(),
+            vec![(1, 1), (1, 3), (3, 3), (3, 1)]
+        );
+    }
+
+    #[test]
+    fn render() {
+        let solution = Solution::new(EXAMPLE_TANGLED);
+        assert_eq!(
+            solution.render(RenderOptions::default()).unwrap(),
+            "─└│┌┐\n\
+             ┐S─┐│\n\
+             └│┐││\n\
+             ─└─┘│\n\
+             └│─┘┌\n"
+        );
+        assert_eq!(
+            solution
+                .render(RenderOptions {
+                    only_loop: true,
+                    mark_interior: true,
+                })
+                .unwrap(),
+            "     \n\
+             \x20S─┐ \n\
+             \x20│I│ \n\
+             \x20└─┘ \n\
+             \x20    \n"
+        );
+    }
+
+    #[test]
+    fn enclosed() {
+        for (input, expected) in [
+            (EXAMPLE_SQUARE, 1),
+            (EXAMPLE_2, 4),
+            (EXAMPLE_3, 8),
+            (EXAMPLE_4, 10),
+        ] {
+            let solution = Solution::new(input);
+            assert_eq!(solution.enclosed_area().unwrap(), expected);
+            assert_eq!(solution.enclosed_area_flood_fill().unwrap(), expected);
+            let inside = solution
+                .classify()
+                .unwrap()
+                .into_iter()
+                .flatten()
+                .filter(|&c| c == Cell::Inside)
+                .count();
+            assert_eq!(inside as u64, expected);
+        }
+    }
+
+    #[test]
+    fn broken_loop() {
+        // the - east of S runs into a | that doesn't connect back to it, even
+        // though following the | the long way round would get back to S
+        let solution = Solution::new("F--7.\n|S-|.\n||...\nLJ...");
+        assert!(solution.main_loop().is_err());
+
+        // and one that runs straight into the ground
+        let solution = Solution::new(".....\n.S-7.\n.|.|.\n.L-..\n.....");
+        assert!(solution.main_loop().is_err());
+        assert!(solution.classify().is_err());
+        assert!(solution.part2().is_err());
+    }
+
+    static EXAMPLE_1: &str = "..F7.
+.FJ|.
+SJ.L7
+|F--J
+LJ...";
+
+    static EXAMPLE_TANGLED: &str = "-L|F7
+7S-7|
+L|7||
+-L-J|
+L|-JF";
+
+    static EXAMPLE_SQUARE: &str = ".....
+.S-7.
+.|.|.
+.L-J.
+.....";
+
+    static EXAMPLE_2: &str = "...........
+.S-------7.
+.|F-----7|.
+.||.....||.
+.||.....||.
+.|L-7.F-J|.
+.|..|.|..|.
+.L--J.L--J.
+...........";
+
+    static EXAMPLE_3: &str = ".F----7F7F7F7F-7....
+.|F--7||||||||FJ....
+.||.FJ||||||||L7....
+FJL7L7LJLJ||LJ.L-7..
+L--J.L7...LJS7F-7L7.
+....F-J..F7FJ|L7L7L7
+....L7.F7||L7|.L7L7|
+.....|FJLJ|FJ|F7|.LJ
+....FJL-7.||.||||...
+....L---J.LJ.LJLJ...";
+
+    static EXAMPLE_4: &str = "FF7FSF7F7F7F7F7F---7
+L|LJ||||||||||||F--J
+FL-7LJLJ||||||LJL-77
+F--JF--7||LJLJ7F7FJ-
+L---JF-JLJ.||-FJLJJ7
+|F|F-JF---7F7-L7L|7|
+|FFJF7L7F-JF7|JL---7
+7-L-JL7||F7|L7F-7F7|
+L.L7LFJ|||||FJL7||LJ
+L7JLJL-JLJLJL--JLJ.L";
+}
@@ -1,99 +1,294 @@
 use std::{
     cmp::{max, min},
-    collections::BTreeSet,
+    collections::{BTreeSet, BinaryHeap},
 };
 
 use prelude::*;
 
-pub struct Solution(Vec<Vec<bool>>);
+pub struct Solution {
+    /// Numbered the way the puzzle does: row by row, top to bottom.
+    galaxies: Vec<(usize, usize)>,
+    empty_rows: BTreeSet<usize>,
+    empty_columns: BTreeSet<usize>,
+}
 
-impl Day for Solution {
-    fn new(input: &str) -> Self {
-        Solution(
-            input
-                .lines()
-                .map(|l| {
-                    l.bytes()
-                        .map(|b| match b {
-                            b'#' => true,
-                            _ => false,
-                        })
-                        .collect_vec()
-                })
-                .collect_vec(),
-        )
+/// Where each position ends up once every empty one in `empties` has grown to
+/// `expansion` of them, in the same order as `positions`.
+fn expand(
+    positions: &[usize],
+    empties: &BTreeSet<usize>,
+    expansion: u64,
+) -> anyhow::Result<Vec<u64>> {
+    // a sorted Vec can be binary searched, while counting a BTreeSet range
+    // visits everything in it
+    let empties = empties.iter().copied().collect_vec();
+    positions
+        .iter()
+        .map(|&p| {
+            // there can't be more empty rows before p than there are rows
+            let before = empties.partition_point(|&e| e < p) as u64;
+            before
+                .checked_mul(expansion)
+                .and_then(|grown| grown.checked_add(p as u64 - before))
+                .ok_or_else(too_large)
+        })
+        .collect()
+}
+
+fn too_large() -> anyhow::Error {
+    anyhow::anyhow!("the expanded universe is too large to measure")
+}
+
+/// The Manhattan distance between two expanded positions.
+fn manhattan((a_i, a_j): (u64, u64), (b_i, b_j): (u64, u64)) -> anyhow::Result<u64> {
+    a_i.abs_diff(b_i)
+        .checked_add(a_j.abs_diff(b_j))
+        .ok_or_else(too_large)
+}
+
+/// The sum of the distances between every pair of `positions` along one axis.
+/// Once they're sorted, each position is farther along than all of the ones
+/// before it, so it contributes `i * position - (sum of the ones before it)`.
+fn sum_1d(mut positions: Vec<u64>) -> anyhow::Result<u64> {
+    positions.sort_unstable();
+
+    let mut before = 0u64;
+    let mut res = 0u64;
+    for (i, p) in positions.into_iter().enumerate() {
+        let contribution = (i as u64).checked_mul(p).ok_or_else(too_large)? - before;
+        res = res.checked_add(contribution).ok_or_else(too_large)?;
+        before = before.checked_add(p).ok_or_else(too_large)?;
     }
+    Ok(res)
+}
 
-    fn part1(&self) -> anyhow::Result<u64> {
-        let mut expanded = self.0.clone();
-        // find all the empty columns first - from right to left so we never have to worry about what we've already inserted
-        for c in (0..self.0[0].len()).rev() {
-            if self.0.iter().all(|row| !row[c]) {
-                // insert a column here
-                for row in expanded.iter_mut() {
-                    row.insert(c, false);
-                }
+/// Two galaxies, by their puzzle numbers, and how far apart they are.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Pair {
+    pub distance: u64,
+    pub a: usize,
+    pub b: usize,
+}
+
+impl Solution {
+    /// Every galaxy's row and column before expansion.  Galaxy `n` in the
+    /// puzzle's numbering is at index `n - 1`.
+    pub fn galaxies(&self) -> &[(usize, usize)] {
+        &self.galaxies
+    }
+
+    /// How far apart galaxies `a` and `b` (numbered from 1) are after
+    /// expansion, or `None` if there's no such galaxy.
+    pub fn distance(&self, a: usize, b: usize, expansion: u64) -> anyhow::Result<Option<u64>> {
+        let galaxy = |n: usize| n.checked_sub(1).and_then(|i| self.galaxies.get(i));
+        let (Some(&(a_i, a_j)), Some(&(b_i, b_j))) = (galaxy(a), galaxy(b)) else {
+            return Ok(None);
+        };
+
+        let (min_i, max_i) = (min(a_i, b_i), max(a_i, b_i));
+        let (min_j, max_j) = (min(a_j, b_j), max(a_j, b_j));
+        // Manhattan distance, but written so I don't have to use signed arithmetic and abs().
+        let length = (max_i - min_i + max_j - min_j) as u64;
+        let empty_rows_between = self.empty_rows.range(min_i..max_i).count() as u64;
+        let empty_columns_between = self.empty_columns.range(min_j..max_j).count() as u64;
+        let empty_between = empty_rows_between + empty_columns_between;
+
+        empty_between
+            .checked_mul(expansion)
+            .and_then(|grown| grown.checked_add(length - empty_between))
+            .map(Some)
+            .ok_or_else(too_large)
+    }
+
+    /// Every galaxy's row and column after expansion.
+    fn expanded(&self, expansion: u64) -> anyhow::Result<Vec<(u64, u64)>> {
+        let (rows, columns): (Vec<usize>, Vec<usize>) = self.galaxies.iter().copied().unzip();
+        let rows = expand(&rows, &self.empty_rows, expansion)?;
+        let columns = expand(&columns, &self.empty_columns, expansion)?;
+        Ok(rows.into_iter().zip(columns).collect())
+    }
+
+    /// The `k` pairs of galaxies that are closest together, closest first.
+    pub fn closest_pairs(&self, k: usize, expansion: u64) -> anyhow::Result<Vec<Pair>> {
+        let expanded = self.expanded(expansion)?;
+        // k comes straight from the caller, so don't allocate for more pairs
+        // than there are
+        let n = expanded.len();
+        let k = k.min(n.saturating_mul(n.saturating_sub(1)) / 2);
+
+        // a max-heap of the closest pairs found so far, so the one to throw
+        // out when a closer one comes along is always on top
+        let mut closest = BinaryHeap::with_capacity(k + 1);
+        for ((a, &a_ij), (b, &b_ij)) in expanded.iter().enumerate().tuple_combinations() {
+            closest.push(Pair {
+                distance: manhattan(a_ij, b_ij)?,
+                a: a + 1,
+                b: b + 1,
+            });
+            if closest.len() > k {
+                closest.pop();
             }
         }
 
-        // now find all the empty rows
-        for r in (0..self.0.len()).rev() {
-            if self.0[r].iter().all(|&x| !x) {
-                expanded.insert(r, expanded[r].clone());
-            }
+        Ok(closest.into_sorted_vec())
+    }
+
+    /// The pair of galaxies that are farthest apart, or `None` if there are
+    /// fewer than two.  Rotating by 45 degrees turns Manhattan distance into
+    /// the larger of the distances along each axis, so the farthest pair is
+    /// the farthest apart along one of them.
+    pub fn farthest_pair(&self, expansion: u64) -> anyhow::Result<Option<Pair>> {
+        if self.galaxies.len() < 2 {
+            return Ok(None);
         }
 
-        let mut galaxies: Vec<(usize, usize)> = vec![];
-        let mut sum_distances = 0;
-
-        for (i, row) in expanded.into_iter().enumerate() {
-            for j in row.iter().positions(|&x| x) {
-                for &(other_i, other_j) in &galaxies {
-                    // Manhattan distance, but written so I don't have to use signed arithmetic and abs().
-                    let length =
-                        max(other_i, i) - min(other_i, i) + max(other_j, j) - min(other_j, j);
-                    log::debug!("found length {length}");
-                    sum_distances += length;
-                }
-                galaxies.push((i, j));
+        let expanded = self.expanded(expansion)?;
+        let sums = expanded
+            .iter()
+            .map(|&(i, j)| i as i128 + j as i128)
+            .collect_vec();
+        let differences = expanded
+            .iter()
+            .map(|&(i, j)| i as i128 - j as i128)
+            .collect_vec();
+
+        let mut farthest: Option<Pair> = None;
+        for rotated in [sums, differences] {
+            let Some((a, b)) = rotated.iter().position_minmax().into_option() else {
+                continue;
+            };
+            let pair = Pair {
+                distance: manhattan(expanded[a], expanded[b])?,
+                a: min(a, b) + 1,
+                b: max(a, b) + 1,
+            };
+            if farthest.is_none_or(|f| pair.distance > f.distance) {
+                farthest = Some(pair);
             }
         }
+        Ok(farthest)
+    }
+
+    /// The sum of the distances between every pair of galaxies, once each
+    /// empty row and column has grown to `expansion` of them.
+    pub fn sum_distances(&self, expansion: u64) -> anyhow::Result<u64> {
+        let (rows, columns): (Vec<usize>, Vec<usize>) = self.galaxies.iter().copied().unzip();
+        let rows = expand(&rows, &self.empty_rows, expansion)?;
+        let columns = expand(&columns, &self.empty_columns, expansion)?;
+        tracing::debug!(
+            expansion,
+            galaxies = self.galaxies.len(),
+            "summing distances"
+        );
 
-        Ok(sum_distances as u64)
+        // Manhattan distance separates into the distance along each axis
+        sum_1d(rows)?
+            .checked_add(sum_1d(columns)?)
+            .ok_or_else(too_large)
     }
+}
 
-    fn part2(&self) -> anyhow::Result<u64> {
-        let empty_columns: BTreeSet<usize> = (0..self.0[0].len())
-            .filter(|&c| self.0.iter().all(|row| !row[c]))
+impl Day for Solution {
+    fn new(input: &str) -> Self {
+        let grid = input
+            .lines()
+            .map(|l| l.bytes().map(|b| b == b'#').collect_vec())
+            .collect_vec();
+
+        let galaxies = grid
+            .iter()
+            .enumerate()
+            .flat_map(|(i, row)| row.iter().positions(|&x| x).map(move |j| (i, j)))
             .collect();
-        let empty_rows: BTreeSet<usize> = (0..self.0.len())
-            .filter(|&r| self.0[r].iter().all(|&x| !x))
+        let empty_columns: BTreeSet<usize> = (0..grid.first().map_or(0, Vec::len))
+            .filter(|&c| grid.iter().all(|row| !row[c]))
+            .collect();
+        let empty_rows: BTreeSet<usize> = (0..grid.len())
+            .filter(|&r| grid[r].iter().all(|&x| !x))
             .collect();
 
-        let mut galaxies: Vec<(usize, usize)> = vec![];
-        let mut sum_distances = 0;
-
-        for (i, row) in self.0.iter().enumerate() {
-            for j in row.iter().positions(|&x| x) {
-                for &(other_i, other_j) in &galaxies {
-                    let min_i = min(other_i, i);
-                    let max_i = max(other_i, i);
-                    let min_j = min(other_j, j);
-                    let max_j = max(other_j, j);
-                    // Manhattan distance, but written so I don't have to use signed arithmetic and abs().
-                    let length = max_i - min_i + max_j - min_j;
-                    log::debug!("found length {length}");
-
-                    let empty_rows_between = empty_rows.range(min_i..max_i).count();
-                    let empty_columns_between = empty_columns.range(min_j..max_j).count();
-
-                    sum_distances += length as u64
-                        + (empty_rows_between + empty_columns_between) as u64 * (1000000 - 1);
-                }
-                galaxies.push((i, j));
-            }
+        Solution {
+            galaxies,
+            empty_rows,
+            empty_columns,
         }
+    }
 
-        Ok(sum_distances)
+    fn part1(&self) -> anyhow::Result<u64> {
+        self.sum_distances(2)
+    }
+
+    fn part2(&self) -> anyhow::Result<u64> {
+        self.sum_distances(1_000_000)
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn example() {
+        let solution = Solution::new(EXAMPLE);
+        assert_eq!(solution.part1().unwrap(), 374);
+        assert_eq!(solution.sum_distances(10).unwrap(), 1030);
+        assert_eq!(solution.sum_distances(100).unwrap(), 8410);
+        assert!(solution.sum_distances(u64::MAX / 4).is_err());
+    }
+
+    #[test]
+    fn pairs() {
+        let solution = Solution::new(EXAMPLE);
+        assert_eq!(solution.galaxies()[4], (5, 1));
+        assert_eq!(solution.distance(5, 9, 2).unwrap(), Some(9));
+        assert_eq!(solution.distance(1, 7, 2).unwrap(), Some(15));
+        assert_eq!(solution.distance(3, 6, 2).unwrap(), Some(17));
+        assert_eq!(solution.distance(8, 9, 2).unwrap(), Some(5));
+        assert_eq!(solution.distance(0, 9, 2).unwrap(), None);
+        assert_eq!(solution.distance(1, 10, 2).unwrap(), None);
+        assert!(solution.distance(1, 9, u64::MAX).is_err());
+
+        for expansion in [0, 1, 2, 10, 1_000_000] {
+            let mut all = (1..=9)
+                .tuple_combinations()
+                .map(|(a, b)| Pair {
+                    distance: solution.distance(a, b, expansion).unwrap().unwrap(),
+                    a,
+                    b,
+                })
+                .collect_vec();
+            all.sort();
+
+            assert_eq!(
+                all.iter().map(|p| p.distance).sum::<u64>(),
+                solution.sum_distances(expansion).unwrap()
+            );
+            assert_eq!(solution.closest_pairs(5, expansion).unwrap(), all[..5]);
+            assert_eq!(solution.closest_pairs(100, expansion).unwrap(), all);
+            assert_eq!(solution.closest_pairs(usize::MAX, expansion).unwrap(), all);
+            assert_eq!(
+                solution.farthest_pair(expansion).unwrap().unwrap().distance,
+                all.last().unwrap().distance
+            );
+        }
+    }
+
+    #[test]
+    fn lonely_galaxy() {
+        let solution = Solution::new("...\n.#.\n...");
+        assert_eq!(solution.farthest_pair(2).unwrap(), None);
+        assert_eq!(solution.closest_pairs(5, 2).unwrap(), vec![]);
+        assert_eq!(solution.sum_distances(2).unwrap(), 0);
+    }
+
+    static EXAMPLE: &str = "...#......
+.......#..
+#.........
+..........
+......#...
+.#........
+.........#
+..........
+.......#..
+#...#.....";
+}
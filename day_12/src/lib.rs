@@ -39,7 +39,7 @@ impl Debug for Spring {
 }
 
 fn count_options(springs: &[Spring], counts: &[u64]) -> u64 {
-    log::debug!("count_options: {springs:?} with counts {counts:?}");
+    tracing::trace!(?springs, ?counts, "count_options");
 
     lazy_static! {
         static ref MEMOIZED: RwLock<HashMap<(Vec<Spring>, Vec<u64>), u64>> =
@@ -166,7 +166,7 @@ mod tests {
 
     #[test]
     fn example_counts() {
-        let _ = env_logger::try_init();
+        diagnostics::init_test();
 
         fn do_line(line: &str) -> u64 {
             let solution = Solution::new(line);
@@ -185,7 +185,7 @@ mod tests {
 
     #[test]
     fn example() {
-        let _ = env_logger::try_init();
+        diagnostics::init_test();
 
         let solution = Solution::new(EXAMPLE);
         assert_eq!(21, solution.part1().unwrap());
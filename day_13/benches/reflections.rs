@@ -0,0 +1,103 @@
+use criterion::{black_box, criterion_group, criterion_main, BenchmarkId, Criterion};
+use day_13::Pattern;
+
+/// A square pattern with a vertical mirror two thirds of the way across and a
+/// single smudge, so that part 2 has something to find.
+fn pattern(size: usize) -> String {
+    let mirror = size * 2 / 3;
+    (0..size)
+        .map(|r| {
+            (0..size)
+                .map(|c| {
+                    let c = if c >= mirror { 2 * mirror - 1 - c } else { c };
+                    let rock = (r * 31 + c * 17 + r * c) % 7 < 3;
+                    let smudge = (r, c) == (size / 2, mirror - 1);
+                    if rock != smudge {
+                        '#'
+                    } else {
+                        '.'
+                    }
+                })
+                .collect::<String>()
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// The cell-by-cell search that `Pattern` used before it packed rows into
+/// bits, kept here to compare against.
+fn cell_by_cell(cells: &[Vec<bool>], needle: usize) -> u64 {
+    let mut res = 0;
+
+    'outer: for i in 1..cells[0].len() {
+        let mut differences = 0;
+        for row in cells {
+            let count = i.min(row.len() - i);
+            let left = row[(i - count)..i].iter();
+            let right = row[i..(i + count)].iter().rev();
+            for (a, b) in left.zip(right) {
+                if a != b {
+                    differences += 1;
+                    if differences > needle {
+                        continue 'outer;
+                    }
+                }
+            }
+        }
+        if differences == needle {
+            res += i as u64;
+        }
+    }
+
+    'outer: for i in 1..cells.len() {
+        let mut differences = 0;
+        let count = i.min(cells.len() - i);
+        let top = cells[(i - count)..i].iter();
+        let bottom = cells[i..(i + count)].iter().rev();
+        for (a, b) in top.zip(bottom) {
+            differences += a.iter().zip(b).filter(|(x, y)| x != y).count();
+            if differences > needle {
+                continue 'outer;
+            }
+        }
+        if differences == needle {
+            res += i as u64 * 100;
+        }
+    }
+
+    res
+}
+
+fn reflections(c: &mut Criterion) {
+    let mut group = c.benchmark_group("reflections");
+    for size in [17, 128, 512, 2048] {
+        let input = pattern(size);
+        let packed = Pattern::from(input.as_str());
+        let cells = input
+            .lines()
+            .map(|line| line.bytes().map(|b| b == b'#').collect::<Vec<_>>())
+            .collect::<Vec<_>>();
+
+        let expected = cell_by_cell(&cells, 1);
+        let found = packed
+            .reflections(1)
+            .into_iter()
+            .filter(|r| r.mismatches() == 1)
+            .map(|r| r.summary())
+            .sum::<u64>();
+        assert_eq!(found, expected, "the two searches disagree at size {size}");
+
+        group.bench_with_input(BenchmarkId::new("bits", size), &packed, |b, packed| {
+            b.iter(|| black_box(packed).reflections(1))
+        });
+        group.bench_with_input(
+            BenchmarkId::new("cell_by_cell", size),
+            &cells,
+            |b, cells| b.iter(|| cell_by_cell(black_box(cells), 1)),
+        );
+    }
+    group.finish();
+}
+
+criterion_group!(benches, reflections);
+criterion_main!(benches);
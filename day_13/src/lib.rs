@@ -1,16 +1,121 @@
-use std::cmp::min;
+use std::{cmp::min, fmt};
 
 use prelude::*;
 
-#[derive(Debug, PartialEq)]
-enum Tile {
-    Ash,
-    Rock,
+/// A row or column of a pattern, one bit per cell, set for rock.  Comparing
+/// two of them is then an XOR and a popcount per 64 cells.
+#[derive(Clone, Debug, PartialEq, Eq)]
+struct Bits(Vec<u64>);
+
+impl Bits {
+    fn new(len: usize) -> Bits {
+        Bits(vec![0; len.div_ceil(64)])
+    }
+
+    fn set(&mut self, i: usize) {
+        self.0[i / 64] |= 1 << (i % 64);
+    }
+
+    fn get(&self, i: usize) -> bool {
+        self.0[i / 64] & (1 << (i % 64)) != 0
+    }
+
+    fn mismatches(&self, other: &Bits) -> usize {
+        self.0
+            .iter()
+            .zip(&other.0)
+            .map(|(a, b)| (a ^ b).count_ones() as usize)
+            .sum()
+    }
+
+    /// The positions where `self` and `other` differ, in order.
+    fn mismatched<'a>(&'a self, other: &'a Bits) -> impl Iterator<Item = usize> + 'a {
+        self.0
+            .iter()
+            .zip(&other.0)
+            .enumerate()
+            .flat_map(|(word, (a, b))| {
+                let mut diff = a ^ b;
+                std::iter::from_fn(move || {
+                    if diff == 0 {
+                        return None;
+                    }
+                    let bit = diff.trailing_zeros() as usize;
+                    diff &= diff - 1;
+                    Some(word * 64 + bit)
+                })
+            })
+    }
 }
 
-use Tile::*;
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Axis {
+    /// A mirror between two columns.
+    Vertical,
+    /// A mirror between two rows.
+    Horizontal,
+}
 
-struct Pattern(Vec<Vec<Tile>>);
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Reflection {
+    pub axis: Axis,
+    /// The number of columns left of a vertical mirror, or rows above a
+    /// horizontal one.
+    pub position: usize,
+    /// The (row, column) of every cell that doesn't match its reflection, in
+    /// reading order.  Each is the one on the left of or above the mirror,
+    /// although fixing its reflection would work just as well.
+    pub smudges: Vec<(usize, usize)>,
+}
+
+impl Reflection {
+    pub fn mismatches(&self) -> usize {
+        self.smudges.len()
+    }
+
+    /// What the puzzle adds up for this mirror.
+    pub fn summary(&self) -> u64 {
+        match self.axis {
+            Axis::Vertical => self.position as u64,
+            Axis::Horizontal => self.position as u64 * 100,
+        }
+    }
+}
+
+pub struct Pattern {
+    width: usize,
+    rows: Vec<Bits>,
+    columns: Vec<Bits>,
+}
+
+/// Rows shorter than the widest one are padded out with ash.
+impl From<&str> for Pattern {
+    fn from(value: &str) -> Self {
+        let lines = value.lines().collect_vec();
+        let width = lines.iter().map(|line| line.len()).max().unwrap_or(0);
+
+        let mut rows = vec![Bits::new(width); lines.len()];
+        let mut columns = vec![Bits::new(lines.len()); width];
+        for (r, line) in lines.into_iter().enumerate() {
+            for (c, b) in line.bytes().enumerate() {
+                match b {
+                    b'#' => {
+                        rows[r].set(c);
+                        columns[c].set(r);
+                    }
+                    b'.' => {}
+                    _ => panic!("unexpected character {b:?}"),
+                }
+            }
+        }
+
+        Pattern {
+            width,
+            rows,
+            columns,
+        }
+    }
+}
 
 impl Pattern {
     fn part1(&self) -> u64 {
@@ -22,81 +127,128 @@ impl Pattern {
     }
 
     fn reflection_differences(&self, needle: usize) -> u64 {
-        let mut vertical_reflections = vec![];
-        let mut horizontal_reflections = vec![];
-
-        'outer: for i in 1..self.0[0].len() {
-            let mut differences = 0;
-            for row in &self.0 {
-                let count = min(i, row.len() - i);
-                assert_ne!(0, count, "we can't process a reflection of nothing");
-                let left = row[(i - count)..i].iter();
-                let right = row[i..(i + count)].iter().rev();
-                for (a, b) in left.zip(right) {
-                    if a != b {
-                        // found a mismatch
-                        differences += 1;
-                        if differences > needle {
-                            continue 'outer;
-                        }
-                    }
-                }
-            }
+        self.reflections(needle)
+            .into_iter()
+            .filter(|r| r.mismatches() == needle)
+            .map(|r| r.summary())
+            .sum()
+    }
+
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn height(&self) -> usize {
+        self.rows.len()
+    }
+
+    /// The cells that don't match their reflection in a mirror, or `None` if
+    /// there are more than `tolerance` of them.
+    fn smudges(
+        &self,
+        axis: Axis,
+        position: usize,
+        tolerance: usize,
+    ) -> Option<Vec<(usize, usize)>> {
+        // a vertical mirror reflects columns, and a horizontal one rows
+        let lines = match axis {
+            Axis::Vertical => &self.columns,
+            Axis::Horizontal => &self.rows,
+        };
 
-            if differences == needle {
-                vertical_reflections.push(i);
+        let count = min(position, lines.len() - position);
+        let pairs = (0..count).map(|k| (position - 1 - k, position + k));
+
+        // count everything before finding where it is, so that most mirrors
+        // are ruled out without looking at individual cells at all
+        let mut mismatches = 0;
+        for (a, b) in pairs.clone() {
+            mismatches += lines[a].mismatches(&lines[b]);
+            if mismatches > tolerance {
+                return None;
             }
         }
 
-        'outer: for i in 1..self.0.len() {
-            let mut differences = 0;
-            let count = min(i, self.0.len() - i);
-            assert_ne!(0, count, "we can't process a reflection of nothing");
-            let top = self.0[(i - count)..i].iter();
-            let bottom = self.0[i..(i + count)].iter().rev();
-            for (a, b) in top.zip(bottom) {
-                let this_differences = a.iter().zip(b).filter(|(x, y)| x != y).count();
-                differences += this_differences;
-                if differences > needle {
-                    continue 'outer;
+        let mut smudges = pairs
+            .flat_map(|(a, b)| {
+                lines[a].mismatched(&lines[b]).map(move |i| match axis {
+                    Axis::Vertical => (i, a),
+                    Axis::Horizontal => (a, i),
+                })
+            })
+            .collect_vec();
+        smudges.sort_unstable();
+        Some(smudges)
+    }
+
+    /// Every mirror, vertical ones first, that has at most `tolerance` cells
+    /// which don't match their reflection.
+    pub fn reflections(&self, tolerance: usize) -> Vec<Reflection> {
+        let vertical = (1..self.width()).map(|i| (Axis::Vertical, i));
+        let horizontal = (1..self.height()).map(|i| (Axis::Horizontal, i));
+
+        vertical
+            .chain(horizontal)
+            .filter_map(|(axis, position)| {
+                Some(Reflection {
+                    axis,
+                    position,
+                    smudges: self.smudges(axis, position, tolerance)?,
+                })
+            })
+            .collect()
+    }
+
+    /// Draw the pattern with its mirror as a line of `|` or `-`, and each smudge
+    /// as what it should have been: `O` for ash or `X` for rock.
+    pub fn render(&self, reflection: &Reflection) -> String {
+        let mut res = String::new();
+        for (r, row) in self.rows.iter().enumerate() {
+            if reflection.axis == Axis::Horizontal && r == reflection.position {
+                res += &"-".repeat(self.width);
+                res.push('\n');
+            }
+            for c in 0..self.width {
+                if reflection.axis == Axis::Vertical && c == reflection.position {
+                    res.push('|');
                 }
+                let rock = row.get(c);
+                res.push(match (rock, reflection.smudges.contains(&(r, c))) {
+                    (false, false) => '.',
+                    (true, false) => '#',
+                    (true, true) => 'O',
+                    (false, true) => 'X',
+                });
             }
+            res.push('\n');
+        }
+        res
+    }
+}
 
-            if differences == needle {
-                horizontal_reflections.push(i);
+impl fmt::Display for Pattern {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for row in &self.rows {
+            for c in 0..self.width {
+                write!(f, "{}", if row.get(c) { '#' } else { '.' })?;
             }
+            writeln!(f)?;
         }
-
-        vertical_reflections.into_iter().sum::<usize>() as u64
-            + (horizontal_reflections.into_iter().sum::<usize>() as u64 * 100)
+        Ok(())
     }
 }
 
 pub struct Solution(Vec<Pattern>);
 
+impl Solution {
+    pub fn patterns(&self) -> &[Pattern] {
+        &self.0
+    }
+}
+
 impl Day for Solution {
     fn new(input: &str) -> Self {
-        Solution(
-            input
-                .split("\n\n")
-                .map(|pattern| {
-                    Pattern(
-                        pattern
-                            .lines()
-                            .map(|line| {
-                                line.bytes()
-                                    .map(|b| match b {
-                                        b'#' => Rock,
-                                        b'.' => Ash,
-                                        _ => panic!("unexpected character {b:?}"),
-                                    })
-                                    .collect_vec()
-                            })
-                            .collect_vec(),
-                    )
-                })
-                .collect_vec(),
-        )
+        Solution(input.split("\n\n").map(Pattern::from).collect_vec())
     }
 
     fn part1(&self) -> anyhow::Result<u64> {
@@ -106,21 +258,7 @@ impl Day for Solution {
             .enumerate()
             .map(|(i, pattern)| {
                 let res = pattern.part1();
-                log::debug!(
-                    "pattern {i} -> {res}\n{}",
-                    pattern
-                        .0
-                        .iter()
-                        .map(|row| {
-                            row.iter()
-                                .map(|tile| match tile {
-                                    Ash => '.',
-                                    Rock => '#',
-                                })
-                                .join("")
-                        })
-                        .join("\n")
-                );
+                tracing::debug!(pattern = i, res, "\n{pattern}");
                 res
             })
             .sum())
@@ -159,4 +297,143 @@ mod test {
 
         assert_eq!(1600, solution.part1().unwrap());
     }
+
+    #[test]
+    fn ragged_rows() {
+        let ragged = Solution::new("#.##..#\n..#.##\n##......#\n##......#\n..#.##\n#.##..#");
+        let padded =
+            Solution::new("#.##..#..\n..#.##...\n##......#\n##......#\n..#.##...\n#.##..#..");
+        assert_eq!(ragged.part1().unwrap(), padded.part1().unwrap());
+        assert_eq!(ragged.part2().unwrap(), padded.part2().unwrap());
+        assert_eq!(ragged.part1().unwrap(), 300);
+    }
+
+    #[test]
+    fn example() {
+        let solution = Solution::new(EXAMPLE);
+        assert_eq!(solution.part1().unwrap(), 405);
+        assert_eq!(solution.part2().unwrap(), 400);
+    }
+
+    #[test]
+    fn reflections() {
+        let solution = Solution::new(EXAMPLE);
+        let [first, second] = solution.patterns() else {
+            panic!("expected two patterns");
+        };
+
+        assert_eq!(
+            first.reflections(0),
+            vec![Reflection {
+                axis: Axis::Vertical,
+                position: 5,
+                smudges: vec![]
+            }]
+        );
+        assert_eq!(
+            second.reflections(1),
+            vec![
+                Reflection {
+                    axis: Axis::Horizontal,
+                    position: 1,
+                    smudges: vec![(0, 4)]
+                },
+                Reflection {
+                    axis: Axis::Horizontal,
+                    position: 4,
+                    smudges: vec![]
+                },
+            ]
+        );
+        // every mirror shows up given enough tolerance
+        assert_eq!(first.reflections(usize::MAX).len(), 8 + 6);
+        assert!(first.reflections(3).iter().all(|r| r.mismatches() <= 3));
+
+        let fixed = first
+            .reflections(1)
+            .into_iter()
+            .find(|r| r.mismatches() == 1)
+            .unwrap();
+        assert_eq!(fixed.axis, Axis::Horizontal);
+        assert_eq!(fixed.smudges, vec![(0, 0)]);
+        assert_eq!(
+            first.render(&fixed),
+            "O.##..##.
+..#.##.#.
+##......#
+---------
+##......#
+..#.##.#.
+..##..##.
+#.#.##.#.
+"
+        );
+        assert_eq!(
+            second.render(&second.reflections(0)[0]),
+            "#...##..#
+#....#..#
+..##..###
+#####.##.
+---------
+#####.##.
+..##..###
+#....#..#
+"
+        );
+    }
+
+    #[test]
+    fn wide_pattern() {
+        // wider and taller than one word of bits, mirrored after column 60
+        let (width, height) = (100, 70);
+        let mut cells = (0..height)
+            .map(|r| {
+                (0..width)
+                    .map(|c: usize| {
+                        let c = if c >= 60 { 119 - c } else { c };
+                        (r * 31 + c * 17 + r * c) % 7 < 3
+                    })
+                    .collect_vec()
+            })
+            .collect_vec();
+        cells[65][30] = !cells[65][30];
+        let input = cells
+            .iter()
+            .map(|row| {
+                row.iter()
+                    .map(|&rock| if rock { '#' } else { '.' })
+                    .join("")
+            })
+            .join("\n");
+
+        let pattern = Pattern::from(input.as_str());
+        assert_eq!(pattern.width(), 100);
+        assert_eq!(pattern.height(), 70);
+        assert_eq!(pattern.to_string(), input + "\n");
+        assert!(pattern.reflections(1).contains(&Reflection {
+            axis: Axis::Vertical,
+            position: 60,
+            smudges: vec![(65, 30)],
+        }));
+        assert!(!pattern
+            .reflections(0)
+            .iter()
+            .any(|r| r.axis == Axis::Vertical && r.position == 60));
+    }
+
+    static EXAMPLE: &str = "#.##..##.
+..#.##.#.
+##......#
+##......#
+..#.##.#.
+..##..##.
+#.#.##.#.
+
+#...##..#
+#....#..#
+..##..###
+#####.##.
+#####.##.
+..##..###
+#....#..#";
 }
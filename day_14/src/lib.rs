@@ -1,124 +1,193 @@
-use std::{cmp::max, collections::BTreeSet};
+use std::fmt;
 
 use prelude::*;
 
-#[derive(Clone, Eq, Hash, PartialEq)]
-pub struct Solution {
-    round: BTreeSet<(usize, usize)>,
-    cube: BTreeSet<(usize, usize)>,
-    max_x: usize,
-    max_y: usize,
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Cell {
+    Empty,
+    Round,
+    Cube,
 }
 
-fn upify(
-    round: &BTreeSet<(usize, usize)>,
-    cube: &BTreeSet<(usize, usize)>,
-) -> BTreeSet<(usize, usize)> {
-    let mut new_round = round.clone();
-    'outer: loop {
-        for &(x, y) in new_round.iter() {
-            if x > 0 && !new_round.contains(&(x - 1, y)) && !cube.contains(&(x - 1, y)) {
-                // nothing above this rock, so move it up and try again
-                new_round.remove(&(x, y));
-                new_round.insert((x - 1, y));
-                continue 'outer;
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Direction {
+    North,
+    West,
+    South,
+    East,
+}
+use Direction::*;
+
+impl Direction {
+    /// The order of the tilts in one spin cycle.
+    pub const SPIN: [Direction; 4] = [North, West, South, East];
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct Platform {
+    width: usize,
+    height: usize,
+    /// Row by row, top to bottom.
+    cells: Vec<Cell>,
+}
+
+impl From<&str> for Platform {
+    fn from(value: &str) -> Self {
+        let lines = value.lines().collect_vec();
+        let width = lines.iter().map(|line| line.len()).max().unwrap_or(0);
+        let height = lines.len();
+
+        let mut cells = vec![Cell::Empty; width * height];
+        for (x, line) in lines.into_iter().enumerate() {
+            for (y, c) in line.bytes().enumerate() {
+                cells[x * width + y] = match c {
+                    b'O' => Cell::Round,
+                    b'#' => Cell::Cube,
+                    _ => Cell::Empty,
+                };
             }
         }
 
-        // nothing moved, so we're done
-        break;
+        Platform {
+            width,
+            height,
+            cells,
+        }
     }
-    new_round
 }
 
-impl Solution {
-    fn rotate_clockwise(self) -> Solution {
-        let translate = |(x, y)| (y, self.max_x - x);
-
-        Solution {
-            round: self.round.into_iter().map(translate).collect(),
-            cube: self.cube.into_iter().map(translate).collect(),
-            max_x: self.max_y,
-            max_y: self.max_x,
-        }
+impl Platform {
+    pub fn width(&self) -> usize {
+        self.width
     }
-}
 
-impl Day for Solution {
-    fn new(input: &str) -> Self {
-        let mut round = BTreeSet::new();
-        let mut cube = BTreeSet::new();
-        let mut max_x = 0;
-        let mut max_y = 0;
+    pub fn height(&self) -> usize {
+        self.height
+    }
 
-        for (x, line) in input.lines().enumerate() {
-            for (y, c) in line.bytes().enumerate() {
-                if c == b'O' {
-                    round.insert((x, y));
-                } else if c == b'#' {
-                    cube.insert((x, y));
+    pub fn get(&self, x: usize, y: usize) -> Cell {
+        self.cells[x * self.width + y]
+    }
+
+    /// Roll every round rock as far as it will go in `direction`.
+    pub fn tilt(&mut self, direction: Direction) {
+        // Each lane is a column (or a row) that rocks roll along, starting
+        // from the edge they roll towards, so one pass along it can drop each
+        // round rock into the first free spot.
+        let (lanes, lane_len) = match direction {
+            North | South => (self.width, self.height),
+            West | East => (self.height, self.width),
+        };
+        let (width, height) = (self.width, self.height);
+        let index = |lane: usize, k: usize| match direction {
+            North => k * width + lane,
+            South => (height - 1 - k) * width + lane,
+            West => lane * width + k,
+            East => lane * width + (width - 1 - k),
+        };
+
+        for lane in 0..lanes {
+            let mut free = 0;
+            for k in 0..lane_len {
+                match self.cells[index(lane, k)] {
+                    Cell::Cube => free = k + 1,
+                    Cell::Round => {
+                        self.cells[index(lane, k)] = Cell::Empty;
+                        self.cells[index(lane, free)] = Cell::Round;
+                        free += 1;
+                    }
+                    Cell::Empty => {}
                 }
-                max_y = max(max_y, y);
             }
-            max_x = max(max_x, x);
         }
+    }
 
-        Solution {
-            round,
-            cube,
-            max_x,
-            max_y,
+    /// Tilt north, then west, then south, then east.
+    pub fn spin_cycle(&mut self) {
+        for direction in Direction::SPIN {
+            self.tilt(direction);
         }
     }
 
-    fn part1(&self) -> anyhow::Result<u64> {
-        let new_round = upify(&self.round, &self.cube);
+    /// Where the layouts after each spin cycle start repeating themselves, as
+    /// `(start, length)`.
+    pub fn find_cycle(&self) -> (u64, u64) {
+        let res = cycle::hashed(self.clone(), Platform::spun);
+        tracing::debug!(cycle_start = res.0, cycle_length = res.1, "found a cycle");
+        res
+    }
+
+    fn spun(&self) -> Platform {
+        let mut res = self.clone();
+        res.spin_cycle();
+        res
+    }
 
-        Ok(new_round
-            .into_iter()
-            .map(|(x, _y)| {
-                let this = self.max_x as u64 - x as u64 + 1;
-                log::debug!("round at {:?} contributed {}", (x, _y), this);
+    /// Run `n` spin cycles.  The platform soon settles into a loop, so this
+    /// only simulates until it repeats a layout, and then skips ahead.
+    pub fn spin_cycles(&mut self, n: u64) {
+        let found = self.find_cycle();
+        *self = cycle::state_at(self.clone(), Platform::spun, found, n);
+    }
+
+    /// The total load on the north support beams.
+    pub fn north_load(&self) -> u64 {
+        self.cells
+            .iter()
+            .positions(|&cell| cell == Cell::Round)
+            .map(|i| {
+                let (x, y) = (i / self.width, i % self.width);
+                let this = (self.height - x) as u64;
+                tracing::trace!(x, y, load = this, "round rock contributed");
                 this
             })
-            .sum())
+            .sum()
     }
+}
 
-    fn part2(&self) -> anyhow::Result<u64> {
-        let mut seen = HashMap::new();
-        let mut state = self.clone();
-
-        for i in 0u64..1000000000 {
-            if let Some(last) = seen.get(&state) {
-                // find the state that matches the cycle
-                let cycle_length = i - last;
-                let target = last + ((1000000000 - last) % cycle_length);
-                state = seen
-                    .into_iter()
-                    .find(|(_state, iteration)| *iteration == target)
-                    .expect("somehow we went past an iteration but didn't find it again")
-                    .0;
-
-                break;
-            }
-            seen.insert(state.clone(), i);
-
-            // and roll the thing around all four directions
-            for _ in 0..4 {
-                state.round = upify(&state.round, &state.cube);
-                state = state.rotate_clockwise();
+impl fmt::Display for Platform {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for row in self.cells.chunks(self.width.max(1)) {
+            for cell in row {
+                write!(
+                    f,
+                    "{}",
+                    match cell {
+                        Cell::Empty => '.',
+                        Cell::Round => 'O',
+                        Cell::Cube => '#',
+                    }
+                )?;
             }
+            writeln!(f)?;
         }
+        Ok(())
+    }
+}
 
-        Ok(state
-            .round
-            .into_iter()
-            .map(|(x, _y)| {
-                let this = self.max_x as u64 - x as u64 + 1;
-                log::debug!("round at {:?} contributed {}", (x, _y), this);
-                this
-            })
-            .sum())
+pub struct Solution(Platform);
+
+impl Solution {
+    pub fn platform(&self) -> &Platform {
+        &self.0
+    }
+}
+
+impl Day for Solution {
+    fn new(input: &str) -> Self {
+        Solution(Platform::from(input))
+    }
+
+    fn part1(&self) -> anyhow::Result<u64> {
+        let mut platform = self.0.clone();
+        platform.tilt(North);
+        Ok(platform.north_load())
+    }
+
+    fn part2(&self) -> anyhow::Result<u64> {
+        let mut platform = self.0.clone();
+        platform.spin_cycles(1000000000);
+        Ok(platform.north_load())
     }
 }
 
@@ -133,6 +202,52 @@ mod test {
         assert_eq!(64, solution.part2().unwrap());
     }
 
+    #[test]
+    fn tilts() {
+        let mut platform = Platform::from(EXAMPLE);
+        platform.tilt(North);
+        assert_eq!(platform.north_load(), 136);
+
+        let mut platform = Platform::from(EXAMPLE);
+        platform.tilt(East);
+        assert_eq!(platform.to_string().lines().next().unwrap(), "....O#....");
+        platform.tilt(West);
+        assert_eq!(platform.to_string().lines().nth(1).unwrap(), "OOO.#....#");
+
+        let mut platform = Platform::from(EXAMPLE);
+        platform.spin_cycle();
+        assert_eq!(
+            platform.to_string(),
+            ".....#....
+....#...O#
+...OO##...
+.OO#......
+.....OOO#.
+.O#...O#.#
+....O#....
+......OOOO
+#...O###..
+#..OO#....
+"
+        );
+        assert_eq!(Platform::from(EXAMPLE).find_cycle(), (3, 7));
+        platform.spin_cycles(2);
+        assert_eq!(
+            platform.to_string(),
+            ".....#....
+....#...O#
+.....##...
+..O#......
+.....OOO#.
+.O#...O#.#
+....O#...O
+.......OOO
+#...O###.O
+#.OOO#...O
+"
+        );
+    }
+
     #[test]
     fn personal_input() {
         let solution = Solution::new(INPUT);
@@ -1,17 +1,96 @@
+use std::{
+    fmt,
+    hash::{BuildHasher, Hasher},
+};
+
 use prelude::*;
 
-fn hash(data: &[u8]) -> u8 {
-    let mut value = 0u8;
+/// The puzzle's HASH algorithm with its constants pulled out: for each byte,
+/// add it, multiply by `multiplier`, and take the remainder mod `modulus`.
+///
+/// It works as a `HashMap`'s hasher too, but keys are hashed through their
+/// `Hash` impls, and a `str` writes a terminator after its bytes, so a label
+/// hashes differently there than it does in [`LensBoxes`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct HolidayHash {
+    multiplier: u64,
+    modulus: u64,
+}
+
+impl HolidayHash {
+    /// # Panics
+    ///
+    /// Panics if `modulus` is zero.
+    pub fn new(multiplier: u64, modulus: u64) -> Self {
+        assert_ne!(modulus, 0, "modulus of zero");
+        HolidayHash {
+            multiplier,
+            modulus,
+        }
+    }
+
+    pub fn multiplier(&self) -> u64 {
+        self.multiplier
+    }
+
+    pub fn modulus(&self) -> u64 {
+        self.modulus
+    }
+}
+
+/// The puzzle's own constants.
+impl Default for HolidayHash {
+    fn default() -> Self {
+        HolidayHash::new(17, 256)
+    }
+}
+
+impl BuildHasher for HolidayHash {
+    type Hasher = HolidayHasher;
 
-    for &i in data.iter() {
-        value = value.wrapping_add(i);
-        value = value.wrapping_mul(17);
+    fn build_hasher(&self) -> HolidayHasher {
+        HolidayHasher {
+            params: *self,
+            state: 0,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct HolidayHasher {
+    params: HolidayHash,
+    state: u64,
+}
+
+impl Hasher for HolidayHasher {
+    fn write(&mut self, bytes: &[u8]) {
+        let HolidayHash {
+            multiplier,
+            modulus,
+        } = self.params;
+        for &b in bytes {
+            // in u128 so that nothing wraps before the remainder is taken
+            let value = (self.state as u128 + b as u128) * multiplier as u128;
+            self.state = (value % modulus as u128) as u64;
+        }
     }
 
-    value
+    fn finish(&self) -> u64 {
+        self.state
+    }
 }
 
-enum Operation<'a> {
+fn hash_with(build: &impl BuildHasher, data: &[u8]) -> u64 {
+    let mut hasher = build.build_hasher();
+    hasher.write(data);
+    hasher.finish()
+}
+
+fn hash(data: &[u8]) -> u8 {
+    hash_with(&HolidayHash::default(), data) as u8
+}
+
+pub enum Operation<'a> {
     Set { label: &'a str, focal_length: u64 },
     Remove(&'a str),
 }
@@ -36,8 +115,175 @@ impl<'a> TryFrom<&'a str> for Operation<'a> {
     }
 }
 
+impl fmt::Display for Operation<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Operation::Set {
+                label,
+                focal_length,
+            } => write!(f, "{label}={focal_length}"),
+            Operation::Remove(label) => write!(f, "{label}-"),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Lens<'a> {
+    pub label: &'a str,
+    pub focal_length: u64,
+}
+
+/// The boxes that the HASHMAP procedure puts lenses in, chosen by hashing each
+/// lens's label with `S`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct LensBoxes<'a, S = HolidayHash> {
+    boxes: Vec<Vec<Lens<'a>>>,
+    build: S,
+}
+
+/// The puzzle's 256 boxes.
+impl Default for LensBoxes<'_> {
+    fn default() -> Self {
+        LensBoxes::with_hasher(HolidayHash::default(), 256)
+    }
+}
+
+impl LensBoxes<'_> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl<'a, S: BuildHasher> LensBoxes<'a, S> {
+    /// `count` boxes, where a lens goes in the box numbered by its label's
+    /// hash, mod `count`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `count` is zero.
+    pub fn with_hasher(build: S, count: usize) -> Self {
+        assert_ne!(count, 0, "there must be at least one box");
+        LensBoxes {
+            boxes: vec![vec![]; count],
+            build,
+        }
+    }
+
+    fn box_for(&mut self, label: &str) -> &mut Vec<Lens<'a>> {
+        let count = self.boxes.len() as u64;
+        let i = hash_with(&self.build, label.as_bytes()) % count;
+        &mut self.boxes[i as usize]
+    }
+
+    pub fn apply(&mut self, operation: Operation<'a>) {
+        match operation {
+            Operation::Remove(label) => {
+                let this_box = self.box_for(label);
+                let loc = this_box.iter().position(|x| x.label == label);
+                if let Some(i) = loc {
+                    this_box.remove(i);
+                }
+            }
+            Operation::Set {
+                label,
+                focal_length,
+            } => {
+                let this_box = self.box_for(label);
+                let loc = this_box.iter().position(|x| x.label == label);
+                if let Some(i) = loc {
+                    this_box[i].focal_length = focal_length
+                } else {
+                    this_box.push(Lens {
+                        label,
+                        focal_length,
+                    })
+                }
+            }
+        }
+    }
+
+    /// Every box, with the lenses in it from front to back.
+    pub fn boxes(&self) -> impl Iterator<Item = (usize, &[Lens<'a>])> {
+        self.boxes.iter().map(Vec::as_slice).enumerate()
+    }
+
+    /// How many lenses are in each box, to see how evenly a hash spreads
+    /// them out.
+    pub fn occupancy(&self) -> Vec<usize> {
+        self.boxes.iter().map(Vec::len).collect()
+    }
+
+    /// Every lens, as (box, slot, lens), counting both from 0.
+    pub fn lenses(&self) -> impl Iterator<Item = (usize, usize, &Lens<'a>)> {
+        self.boxes().flat_map(|(box_number, this_box)| {
+            this_box
+                .iter()
+                .enumerate()
+                .map(move |(slot, lens)| (box_number, slot, lens))
+        })
+    }
+
+    pub fn focusing_power(&self) -> u64 {
+        self.lenses()
+            .map(|(box_number, slot, lens)| {
+                (box_number as u64 + 1) * (slot as u64 + 1) * lens.focal_length
+            })
+            .sum()
+    }
+}
+
+/// The boxes that have any lenses in them, like `Box 0: [rn 1] [cm 2]`.
+impl<S: BuildHasher> fmt::Display for LensBoxes<'_, S> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (box_number, this_box) in self.boxes().filter(|(_, b)| !b.is_empty()) {
+            write!(f, "Box {box_number}:")?;
+            for lens in this_box {
+                write!(f, " [{} {}]", lens.label, lens.focal_length)?;
+            }
+            writeln!(f)?;
+        }
+        Ok(())
+    }
+}
+
 pub struct Solution(Vec<String>);
 
+impl Solution {
+    pub fn operations(&self) -> impl Iterator<Item = anyhow::Result<Operation<'_>>> {
+        self.0.iter().map(|step| Operation::try_from(step.as_str()))
+    }
+
+    pub fn boxes(&self) -> anyhow::Result<LensBoxes<'_>> {
+        self.boxes_with(HolidayHash::default(), 256)
+    }
+
+    /// The boxes after all of the steps, using `count` boxes and some other hash.
+    pub fn boxes_with<S: BuildHasher>(
+        &self,
+        build: S,
+        count: usize,
+    ) -> anyhow::Result<LensBoxes<'_, S>> {
+        let mut boxes = LensBoxes::with_hasher(build, count);
+        for operation in self.operations() {
+            boxes.apply(operation?);
+        }
+        Ok(boxes)
+    }
+
+    /// The boxes after every step, the way the puzzle lists them.
+    pub fn trace(&self) -> anyhow::Result<String> {
+        let mut boxes = LensBoxes::new();
+        let mut res = String::new();
+        for operation in self.operations() {
+            let operation = operation?;
+            res += &format!("After \"{operation}\":\n");
+            boxes.apply(operation);
+            res += &format!("{boxes}\n");
+        }
+        Ok(res)
+    }
+}
+
 impl Day for Solution {
     fn new(input: &str) -> Self {
         Solution(input.trim().split(',').map(|s| s.to_owned()).collect())
@@ -48,47 +294,7 @@ impl Day for Solution {
     }
 
     fn part2(&self) -> anyhow::Result<u64> {
-        const EMPTY: Vec<(&str, u64)> = vec![]; // workaround, because [vec![]; 256] complains that Vec is not Copy
-        let mut boxes: [Vec<(&str, u64)>; 256] = [EMPTY; 256];
-
-        for step in &self.0 {
-            let step = Operation::try_from(step.as_str())?;
-
-            match step {
-                Operation::Remove(label) => {
-                    let this_box = &mut boxes[hash(label.as_bytes()) as usize];
-                    let loc = this_box.iter().position(|x| x.0 == label);
-                    if let Some(i) = loc {
-                        this_box.remove(i);
-                    }
-                }
-                Operation::Set {
-                    label,
-                    focal_length,
-                } => {
-                    let this_box = &mut boxes[hash(label.as_bytes()) as usize];
-                    let loc = this_box.iter().position(|x| x.0 == label);
-                    if let Some(i) = loc {
-                        this_box[i].1 = focal_length
-                    } else {
-                        this_box.push((label, focal_length))
-                    }
-                }
-            }
-        }
-
-        Ok(boxes
-            .iter()
-            .enumerate()
-            .flat_map(|(box_number, this_box)| {
-                this_box
-                    .iter()
-                    .enumerate()
-                    .map(move |(slot, &(_label, focal_length))| {
-                        (box_number as u64 + 1) * (slot as u64 + 1) * focal_length
-                    })
-            })
-            .sum())
+        Ok(self.boxes()?.focusing_power())
     }
 }
 
@@ -101,4 +307,123 @@ mod test {
         assert_eq!(30, hash(b"rn=1"));
         assert_eq!(253, hash(b"cm-"));
     }
+
+    #[test]
+    fn example() {
+        let solution = Solution::new(EXAMPLE);
+        assert_eq!(solution.part1().unwrap(), 1320);
+        assert_eq!(solution.part2().unwrap(), 145);
+
+        let boxes = solution.boxes().unwrap();
+        assert_eq!(
+            boxes
+                .lenses()
+                .map(|(b, s, l)| (b, s, l.label))
+                .collect_vec(),
+            vec![
+                (0, 0, "rn"),
+                (0, 1, "cm"),
+                (3, 0, "ot"),
+                (3, 1, "ab"),
+                (3, 2, "pc")
+            ]
+        );
+        assert_eq!(
+            boxes.to_string(),
+            "Box 0: [rn 1] [cm 2]\nBox 3: [ot 7] [ab 5] [pc 6]\n"
+        );
+    }
+
+    #[test]
+    fn trace() {
+        let trace = Solution::new(EXAMPLE).trace().unwrap();
+        assert!(trace.starts_with(
+            "After \"rn=1\":
+Box 0: [rn 1]
+
+After \"cm-\":
+Box 0: [rn 1]
+
+After \"qp=3\":
+Box 0: [rn 1]
+Box 1: [qp 3]
+
+"
+        ));
+        assert!(trace.ends_with(
+            "After \"ot=7\":
+Box 0: [rn 1] [cm 2]
+Box 3: [ot 7] [ab 5] [pc 6]
+
+"
+        ));
+    }
+
+    #[test]
+    fn hasher() {
+        let mut hasher = HolidayHash::default().build_hasher();
+        hasher.write(b"HASH");
+        assert_eq!(hasher.finish(), 52);
+
+        // big enough that a u64 would overflow partway through
+        let mut hasher = HolidayHash::new(u64::MAX - 1, u64::MAX).build_hasher();
+        hasher.write(b"rn");
+        assert!(hasher.finish() < u64::MAX);
+
+        let mut map: std::collections::HashMap<String, u64, HolidayHash> = Default::default();
+        map.insert("rn".to_owned(), 1);
+        map.insert("cm".to_owned(), 2);
+        assert_eq!(map.get("rn"), Some(&1));
+        assert_eq!(map.get("qp"), None);
+
+        // the str's terminator goes through the hash after its bytes
+        assert_eq!(hash_with(&HolidayHash::default(), b"HASH\xff"), 99);
+        assert_eq!(HolidayHash::default().hash_one("HASH"), 99);
+    }
+
+    #[test]
+    fn other_hashes() {
+        let solution = Solution::new(EXAMPLE);
+        let lenses = |boxes: &LensBoxes<'_, HolidayHash>| {
+            boxes
+                .lenses()
+                .map(|(_, _, lens)| (lens.label.to_owned(), lens.focal_length))
+                .sorted()
+                .collect_vec()
+        };
+        let puzzle = solution.boxes().unwrap();
+
+        let occupancy = |multiplier, modulus| {
+            solution
+                .boxes_with(HolidayHash::new(multiplier, modulus), modulus as usize)
+                .unwrap()
+                .occupancy()
+        };
+        let puzzle_occupancy = occupancy(17, 256);
+        assert_eq!(puzzle_occupancy.len(), 256);
+        assert_eq!((puzzle_occupancy[0], puzzle_occupancy[3]), (2, 3));
+        assert_eq!(puzzle_occupancy.iter().filter(|&&n| n > 0).count(), 2);
+        // a different multiplier gives every lens a box of its own
+        let spread = occupancy(31, 256);
+        assert_ne!(spread, puzzle_occupancy);
+        assert_eq!(spread.iter().max(), Some(&1));
+        assert_eq!(occupancy(1, 7), vec![1, 1, 0, 1, 0, 1, 1]);
+
+        for (multiplier, modulus) in [(17, 256), (31, 256), (17, 1024), (1, 7)] {
+            let boxes = solution
+                .boxes_with(HolidayHash::new(multiplier, modulus), modulus as usize)
+                .unwrap();
+            // the same lenses end up somewhere, just spread out differently
+            assert_eq!(lenses(&boxes), lenses(&puzzle));
+            assert_eq!(
+                boxes.boxes().map(|(_, b)| b.len()).sum::<usize>(),
+                puzzle.lenses().count()
+            );
+        }
+
+        let boxes = solution.boxes_with(HolidayHash::new(17, 256), 256).unwrap();
+        assert_eq!(boxes.focusing_power(), 145);
+    }
+
+    static EXAMPLE: &str = "rn=1,cm-,qp=3,cm=2,qp-,pc=4,ot=9,ab=5,pc-,pc=6,ot=7";
 }
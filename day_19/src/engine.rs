@@ -0,0 +1,447 @@
+//! A small rule engine: workflows of comparisons over named numeric attributes.
+//!
+//! Each workflow is a list of rules tried in order.  A rule either always applies or compares one
+//! attribute against a constant, and sends the part on to another workflow, or accepts or rejects
+//! it.  Every attribute has its own domain of possible values, which is what
+//! [`Engine::count_accepted`] counts over.
+
+use std::ops::RangeInclusive;
+
+use prelude::*;
+
+use range_set::RangeSet;
+
+/// The set of values an attribute may take.
+pub type Domain = RangeSet<[RangeInclusive<u64>; 10]>;
+
+/// The attributes a part has, and the values each of them may take.
+#[derive(Clone, Debug)]
+pub struct Attributes {
+    names: Vec<String>,
+    domains: Vec<Domain>,
+}
+
+impl Attributes {
+    /// Attributes with the given names, all sharing the same domain.
+    pub fn new<S: Into<String>>(
+        names: impl IntoIterator<Item = S>,
+        domain: RangeInclusive<u64>,
+    ) -> Self {
+        let names = names.into_iter().map(Into::into).collect::<Vec<String>>();
+        let domains = vec![Domain::from_ranges(&[domain]); names.len()];
+
+        Attributes { names, domains }
+    }
+
+    /// Replaces the domain of one attribute.
+    pub fn with_domain(
+        mut self,
+        name: &str,
+        ranges: &[RangeInclusive<u64>],
+    ) -> anyhow::Result<Self> {
+        let index = self
+            .index(name)
+            .ok_or_else(|| anyhow::anyhow!("there is no attribute {name:?}"))?;
+        self.domains[index] = Domain::from_ranges(ranges);
+        Ok(self)
+    }
+
+    pub fn names(&self) -> &[String] {
+        &self.names
+    }
+
+    pub fn index(&self, name: &str) -> Option<usize> {
+        self.names.iter().position(|n| n == name)
+    }
+
+    pub fn domain(&self, index: usize) -> &Domain {
+        &self.domains[index]
+    }
+
+    /// Parses a part like `{x=787,m=2655,a=1222,s=2876}`, returning its values in attribute
+    /// order.  The attributes may be listed in any order, but each must appear exactly once.
+    pub fn parse_values(&self, part: &str) -> anyhow::Result<Vec<u64>> {
+        let inner = part
+            .strip_prefix('{')
+            .and_then(|p| p.strip_suffix('}'))
+            .ok_or_else(|| anyhow::anyhow!("{part:?} isn't wrapped in {{}}"))?;
+
+        let mut values = vec![None; self.names.len()];
+        for assignment in inner.split(',') {
+            let (name, value) = assignment
+                .split_once('=')
+                .ok_or_else(|| anyhow::anyhow!("{assignment:?} has no ="))?;
+            let index = self
+                .index(name)
+                .ok_or_else(|| anyhow::anyhow!("there is no attribute {name:?}"))?;
+            let value = value
+                .parse()
+                .with_context(|| format!("bad value for {name:?}"))?;
+            anyhow::ensure!(
+                values[index].replace(value).is_none(),
+                "{name:?} is given twice"
+            );
+        }
+
+        values
+            .into_iter()
+            .zip(&self.names)
+            .map(|(value, name)| value.ok_or_else(|| anyhow::anyhow!("{name:?} is missing")))
+            .collect()
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Comparison {
+    Lt,
+    Le,
+    Gt,
+    Ge,
+    Eq,
+}
+
+impl Comparison {
+    pub fn holds(self, value: u64, target: u64) -> bool {
+        match self {
+            Comparison::Lt => value < target,
+            Comparison::Le => value <= target,
+            Comparison::Gt => value > target,
+            Comparison::Ge => value >= target,
+            Comparison::Eq => value == target,
+        }
+    }
+
+    // All the values for which the comparison holds, or None if there aren't any.
+    fn matching(self, target: u64) -> Option<RangeInclusive<u64>> {
+        match self {
+            Comparison::Lt => Some(0..=target.checked_sub(1)?),
+            Comparison::Le => Some(0..=target),
+            Comparison::Gt => Some(target.checked_add(1)?..=u64::MAX),
+            Comparison::Ge => Some(target..=u64::MAX),
+            Comparison::Eq => Some(target..=target),
+        }
+    }
+}
+
+impl TryFrom<&str> for Comparison {
+    type Error = anyhow::Error;
+
+    fn try_from(value: &str) -> anyhow::Result<Self> {
+        Ok(match value {
+            "<" => Comparison::Lt,
+            "<=" => Comparison::Le,
+            ">" => Comparison::Gt,
+            ">=" => Comparison::Ge,
+            "==" => Comparison::Eq,
+            _ => anyhow::bail!("invalid comparison {value:?}"),
+        })
+    }
+}
+
+#[derive(Debug)]
+pub enum Condition {
+    Always,
+    /// Compares the attribute at this index against a constant.
+    Compare(usize, Comparison, u64),
+}
+
+#[derive(Debug)]
+pub enum Disposition {
+    Next(String),
+    Accept,
+    Reject,
+}
+
+impl From<&str> for Disposition {
+    fn from(value: &str) -> Self {
+        match value {
+            "A" => Disposition::Accept,
+            "R" => Disposition::Reject,
+            _ => Disposition::Next(value.to_owned()),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct Rule {
+    pub condition: Condition,
+    pub disposition: Disposition,
+}
+
+lazy_static::lazy_static! {
+    static ref COMPARISON: Regex = Regex::new(r"^(\w+?)(<=|>=|==|<|>)(\d+)$").unwrap();
+}
+
+pub struct Engine {
+    attributes: Attributes,
+    workflows: HashMap<String, Vec<Rule>>,
+    start: String,
+}
+
+impl Engine {
+    /// Parses one workflow per line, like `px{a<2006:qkq,m>2090:A,rfg}`.  Evaluation begins at
+    /// the workflow called `start`.
+    pub fn parse(attributes: Attributes, start: &str, workflows: &str) -> anyhow::Result<Self> {
+        let workflows = workflows
+            .lines()
+            .map(|line| -> anyhow::Result<(String, Vec<Rule>)> {
+                let (name, rest) = line
+                    .split_once('{')
+                    .ok_or_else(|| anyhow::anyhow!("could not find a {{ in {line:?}"))?;
+                let rest = rest
+                    .strip_suffix('}')
+                    .ok_or_else(|| anyhow::anyhow!("could not find a }} in {line:?}"))?;
+                let rules = rest
+                    .split(',')
+                    .map(|rule| parse_rule(&attributes, rule))
+                    .collect::<anyhow::Result<_>>()
+                    .with_context(|| format!("in workflow {name:?}"))?;
+
+                Ok((name.to_owned(), rules))
+            })
+            .collect::<anyhow::Result<HashMap<_, _>>>()?;
+
+        anyhow::ensure!(
+            workflows.contains_key(start),
+            "could not find workflow {start:?}"
+        );
+        for (name, rules) in &workflows {
+            for rule in rules {
+                if let Disposition::Next(next) = &rule.disposition {
+                    anyhow::ensure!(
+                        workflows.contains_key(next),
+                        "workflow {name:?} refers to missing workflow {next:?}"
+                    );
+                }
+            }
+        }
+
+        Ok(Engine {
+            attributes,
+            workflows,
+            start: start.to_owned(),
+        })
+    }
+
+    pub fn attributes(&self) -> &Attributes {
+        &self.attributes
+    }
+
+    /// Runs a part, given as values in attribute order, through the workflows.
+    pub fn is_accepted(&self, values: &[u64]) -> anyhow::Result<bool> {
+        anyhow::ensure!(
+            values.len() == self.attributes.names.len(),
+            "expected {} values, got {}",
+            self.attributes.names.len(),
+            values.len()
+        );
+
+        let mut wf_name = self.start.as_str();
+        let mut visited = HashSet::new();
+        tracing::debug!(?values, "evaluating");
+
+        loop {
+            anyhow::ensure!(
+                visited.insert(wf_name),
+                "the workflows loop forever through {wf_name:?}"
+            );
+            tracing::trace!(workflow = wf_name, "looking at workflow");
+            let workflow = &self.workflows[wf_name];
+            tracing::trace!(rules = ?workflow, "workflow rules");
+
+            let chosen = workflow.iter().find(|rule| match rule.condition {
+                Condition::Always => true,
+                Condition::Compare(attribute, comparison, target) => {
+                    comparison.holds(values[attribute], target)
+                }
+            });
+
+            match chosen.map(|rule| &rule.disposition) {
+                Some(Disposition::Accept) => return Ok(true),
+                Some(Disposition::Reject) => return Ok(false),
+                Some(Disposition::Next(name)) => wf_name = name,
+                None => anyhow::bail!("none of the rules in {wf_name:?} matched"),
+            }
+        }
+    }
+
+    /// Counts how many combinations of attribute values, each drawn from its domain, are
+    /// accepted.
+    pub fn count_accepted(&self) -> anyhow::Result<u64> {
+        self.count_from(&self.start, self.attributes.domains.clone(), &mut vec![])
+    }
+
+    fn count_from<'a>(
+        &'a self,
+        wf_name: &'a str,
+        mut possibilities: Vec<Domain>,
+        path: &mut Vec<&'a str>,
+    ) -> anyhow::Result<u64> {
+        // every combination that got here would come back around to here forever
+        anyhow::ensure!(
+            !path.contains(&wf_name),
+            "the workflows loop forever through {wf_name:?}"
+        );
+        path.push(wf_name);
+
+        let mut res = 0u64;
+
+        for rule in &self.workflows[wf_name] {
+            if possibilities.iter().any(|r| r.is_empty()) {
+                // no amount of set-intersections is ever going to bring this back...
+                break;
+            }
+
+            let mut recur_possibilities = possibilities.clone();
+
+            match rule.condition {
+                Condition::Compare(attribute, comparison, target) => {
+                    let recur_ranges = &mut recur_possibilities[attribute];
+                    let inverse = match comparison.matching(target) {
+                        Some(matching) => shrink_range(recur_ranges, matching),
+                        None => std::mem::replace(recur_ranges, Domain::new()),
+                    };
+                    possibilities[attribute] = inverse;
+                }
+                // we will recur on everything that's possible so far, and then we're done
+                Condition::Always => {}
+            }
+
+            let accepted = match &rule.disposition {
+                Disposition::Accept => count(&recur_possibilities)?,
+                // do nothing, but let the shrunken range continue down the list of rules
+                Disposition::Reject => 0,
+                // nothing can take this branch, so don't go looking for loops down it
+                Disposition::Next(_) if recur_possibilities.iter().any(|r| r.is_empty()) => 0,
+                Disposition::Next(next) => self.count_from(next, recur_possibilities, path)?,
+            };
+            res = res
+                .checked_add(accepted)
+                .ok_or_else(|| anyhow::anyhow!("too many combinations to count"))?;
+
+            if let Condition::Always = rule.condition {
+                // nothing is left over for the rules after this one
+                break;
+            }
+        }
+
+        path.pop();
+        Ok(res)
+    }
+}
+
+fn parse_rule(attributes: &Attributes, rule: &str) -> anyhow::Result<Rule> {
+    let Some((l, r)) = rule.split_once(':') else {
+        return Ok(Rule {
+            condition: Condition::Always,
+            disposition: rule.into(),
+        });
+    };
+
+    let captures = COMPARISON
+        .captures(l)
+        .ok_or_else(|| anyhow::anyhow!("{l:?} isn't a comparison"))?;
+    let name = &captures[1];
+    let attribute = attributes
+        .index(name)
+        .ok_or_else(|| anyhow::anyhow!("there is no attribute {name:?}"))?;
+    let comparison = captures[2].try_into()?;
+    let target = captures[3].parse().context("bad integer")?;
+
+    Ok(Rule {
+        condition: Condition::Compare(attribute, comparison, target),
+        disposition: r.into(),
+    })
+}
+
+// Intersects ranges with intersect_with, and returns ranges & !intersect_with
+fn shrink_range(ranges: &mut Domain, intersect_with: RangeInclusive<u64>) -> Domain {
+    // since there's no intersect() function in RangeSet, but doing inserts and removals will *return* the ranges
+    let intersected = ranges.remove_range(intersect_with);
+    if let Some(mut r) = intersected {
+        // put the intersection where we expected, and return the inverse of the intersection
+        std::mem::swap(&mut r, ranges);
+        r
+    } else {
+        // there was no intersection, so ranges becomes empty and everything that *was* in ranges should be returned
+        std::mem::replace(ranges, Domain::new())
+    }
+}
+
+fn count(ranges: &[Domain]) -> anyhow::Result<u64> {
+    ranges
+        .iter()
+        .map(|r| {
+            r.as_ref()
+                .iter()
+                .map(|std_range| (std_range.end() - std_range.start()).checked_add(1))
+                .try_fold(0u64, |sum, size| sum.checked_add(size?))
+        })
+        .try_fold(1u64, |product, size| product.checked_mul(size?))
+        .ok_or_else(|| anyhow::anyhow!("too many combinations to count"))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn brute_force(engine: &Engine, hp: RangeInclusive<u64>, ac: &[u64]) -> u64 {
+        hp.cartesian_product(ac.iter().copied())
+            .filter(|&(hp, ac)| engine.is_accepted(&[hp, ac]).unwrap())
+            .count() as u64
+    }
+
+    #[test]
+    fn custom_attributes() {
+        let attributes = Attributes::new(["hp", "ac"], 0..=20)
+            .with_domain("ac", &[10..=12, 15..=18])
+            .unwrap();
+        let engine = Engine::parse(
+            attributes,
+            "start",
+            "start{hp==0:R,ac>=16:tough,hp<=5:weak,A}\n\
+             tough{hp>=10:A,ac==16:R,weak}\n\
+             weak{ac<11:A,R}",
+        )
+        .unwrap();
+
+        assert!(!engine.is_accepted(&[0, 17]).unwrap());
+        assert!(engine.is_accepted(&[12, 17]).unwrap());
+        assert!(!engine.is_accepted(&[3, 16]).unwrap());
+        assert!(engine.is_accepted(&[3, 10]).unwrap());
+        assert!(engine.is_accepted(&[7, 11]).unwrap());
+
+        let values = engine.attributes().parse_values("{ac=17,hp=3}").unwrap();
+        assert_eq!(vec![3, 17], values);
+        assert!(!engine.is_accepted(&values).unwrap());
+
+        assert_eq!(
+            brute_force(&engine, 0..=20, &[10, 11, 12, 15, 16, 17, 18]),
+            engine.count_accepted().unwrap()
+        );
+    }
+
+    #[test]
+    fn bad_workflows() {
+        let attributes = || Attributes::new(["n"], 1..=10);
+
+        assert!(Engine::parse(attributes(), "in", "in{n<5:nowhere,A}").is_err());
+        assert!(Engine::parse(attributes(), "in", "in{q<5:R,A}").is_err());
+        assert!(Engine::parse(attributes(), "in", "in{n!=5:R,A}").is_err());
+        assert!(Engine::parse(attributes(), "start", "in{A}").is_err());
+
+        let looping = Engine::parse(attributes(), "in", "in{n<5:A,out}\nout{n>8:R,in}").unwrap();
+        assert!(looping.is_accepted(&[2]).unwrap());
+        assert!(looping.is_accepted(&[6]).is_err());
+        assert!(looping.count_accepted().is_err());
+
+        // with no attributes there's exactly one (empty) part
+        let nothing = Engine::parse(Attributes::new([""; 0], 1..=10), "in", "in{A,R}").unwrap();
+        assert_eq!(nothing.count_accepted().unwrap(), 1);
+        let nothing = Engine::parse(Attributes::new([""; 0], 1..=10), "in", "in{R,A}").unwrap();
+        assert_eq!(nothing.count_accepted().unwrap(), 0);
+
+        let huge = Engine::parse(Attributes::new(["a", "b"], 0..=u64::MAX), "in", "in{A}").unwrap();
+        assert!(huge.count_accepted().is_err());
+    }
+}
@@ -0,0 +1,40 @@
+use wasm_bindgen::prelude::*;
+
+crate::common_day!(day_05, Day05, u64, u64);
+
+#[wasm_bindgen]
+impl Day05 {
+    fn composed(&self) -> Result<day_05::RangeMap, JsValue> {
+        self.0
+            .compose("seed", "location")
+            .map_err(|e| JsValue::from(e.to_string()))
+    }
+
+    /// The composed seed-to-location map, in the almanac's own format.
+    pub fn composed_almanac(&self) -> Result<String, JsValue> {
+        self.composed().map(|map| map.to_string())
+    }
+
+    pub fn composed_csv(&self) -> Result<String, JsValue> {
+        self.composed().map(|map| map.to_csv())
+    }
+
+    /// The pieces of the composed map as `[source, destination, length]`,
+    /// including the identity pieces up until the last explicit segment.
+    pub fn composed_pieces(&self) -> Result<JsValue, JsValue> {
+        let map = self.composed()?;
+        let end = map
+            .segments()
+            .map(|(from, _to, count)| from + count)
+            .max()
+            .unwrap_or(0);
+
+        Ok(serde_wasm_bindgen::to_value(
+            &map.pieces()
+                .into_iter()
+                .filter(|&(from, _to, _count)| from < end)
+                .collect::<Vec<_>>(),
+        )
+        .unwrap())
+    }
+}
@@ -0,0 +1,47 @@
+use serde::Serialize;
+use wasm_bindgen::prelude::*;
+
+crate::common_day!(day_07, Day07, u64, u64);
+
+#[derive(Serialize)]
+struct Explanation {
+    cards: String,
+    bet: u64,
+    hand_type: String,
+    rank: u64,
+    substitute: Option<char>,
+    tiebreak: Option<usize>,
+}
+
+#[wasm_bindgen]
+impl Day07 {
+    /// Explain every hand's placement, weakest first, under the part 1 rules or
+    /// (if `jokers` is set) the part 2 rules.
+    pub fn explain(&self, jokers: bool) -> JsValue {
+        let explained = if jokers {
+            self.0.explain(&day_07::Jokers)
+        } else {
+            self.0.explain(&day_07::Standard)
+        };
+
+        let explained = explained
+            .into_iter()
+            .map(|e| Explanation {
+                cards: e
+                    .ranked
+                    .hand
+                    .cards()
+                    .map(day_07::Card::label)
+                    .iter()
+                    .collect(),
+                bet: e.ranked.hand.bet(),
+                hand_type: format!("{:?}", e.ranked.hand_type),
+                rank: e.ranked.rank,
+                substitute: e.substitute.map(day_07::Card::label),
+                tiebreak: e.tiebreak,
+            })
+            .collect::<Vec<_>>();
+
+        serde_wasm_bindgen::to_value(&explained).unwrap()
+    }
+}
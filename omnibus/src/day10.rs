@@ -4,6 +4,34 @@ use wasm_bindgen::prelude::*;
 
 crate::common_day!(day_10, Day10, u64, u64);
 
+#[wasm_bindgen]
+pub struct Classification {
+    width: usize,
+    cells: Vec<u8>,
+    starting_tile: char,
+}
+
+#[wasm_bindgen]
+impl Classification {
+    #[wasm_bindgen(getter)]
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    /// One byte per tile, row by row: 0 for the loop, 1 for inside it, 2 for
+    /// outside it, and 3 for pipes outside it that aren't part of it.
+    #[wasm_bindgen(getter)]
+    pub fn cells(&self) -> Vec<u8> {
+        self.cells.clone()
+    }
+
+    /// What `S` must be, written as it would be in the input.
+    #[wasm_bindgen(getter)]
+    pub fn starting_tile(&self) -> char {
+        self.starting_tile
+    }
+}
+
 #[wasm_bindgen]
 impl Day10 {
     pub fn get_distances(&self) -> Result<JsValue, JsValue> {
@@ -20,4 +48,42 @@ impl Day10 {
                 .unwrap()
             })
     }
+
+    /// The grid drawn with box-drawing characters.
+    pub fn render(&self, only_loop: bool, mark_interior: bool) -> Result<String, JsValue> {
+        self.0
+            .render(day_10::RenderOptions {
+                only_loop,
+                mark_interior,
+            })
+            .map_err(|e| JsValue::from(e.to_string()))
+    }
+
+    pub fn classify(&self) -> Result<Classification, JsValue> {
+        let starting_tile = self
+            .0
+            .starting_tile()
+            .map_err(|e| JsValue::from(e.to_string()))?;
+        let rows = self
+            .0
+            .classify()
+            .map_err(|e| JsValue::from(e.to_string()))?;
+
+        let width = rows.iter().map(Vec::len).max().unwrap_or(0);
+        let cells = rows
+            .into_iter()
+            .flat_map(|row| {
+                let padding = width - row.len();
+                row.into_iter()
+                    .chain(std::iter::repeat_n(day_10::Cell::Outside, padding))
+                    .map(|cell| cell as u8)
+            })
+            .collect();
+
+        Ok(Classification {
+            width,
+            cells,
+            starting_tile: starting_tile.symbol(),
+        })
+    }
 }
@@ -0,0 +1,73 @@
+use serde::Serialize;
+use wasm_bindgen::prelude::*;
+
+crate::common_day!(day_11, Day11, u64, u64);
+
+#[derive(Serialize)]
+struct Pair {
+    a: usize,
+    b: usize,
+    distance: u64,
+}
+
+impl From<day_11::Pair> for Pair {
+    fn from(pair: day_11::Pair) -> Self {
+        Pair {
+            a: pair.a,
+            b: pair.b,
+            distance: pair.distance,
+        }
+    }
+}
+
+fn to_value<T: Serialize>(value: &T) -> Result<JsValue, JsValue> {
+    let serializer =
+        serde_wasm_bindgen::Serializer::new().serialize_large_number_types_as_bigints(true);
+    value
+        .serialize(&serializer)
+        .map_err(|e| JsValue::from(e.to_string()))
+}
+
+#[wasm_bindgen]
+impl Day11 {
+    /// The sum of the distances between every pair of galaxies, once each
+    /// empty row and column has grown to `expansion` of them.
+    pub fn sum_distances(&self, expansion: u64) -> Result<u64, JsValue> {
+        self.0
+            .sum_distances(expansion)
+            .map_err(|e| JsValue::from(e.to_string()))
+    }
+
+    pub fn galaxy_count(&self) -> usize {
+        self.0.galaxies().len()
+    }
+
+    /// How far apart galaxies `a` and `b` (numbered from 1) are after
+    /// expansion.
+    pub fn distance(&self, a: usize, b: usize, expansion: u64) -> Result<Option<u64>, JsValue> {
+        self.0
+            .distance(a, b, expansion)
+            .map_err(|e| JsValue::from(e.to_string()))
+    }
+
+    /// The `k` closest pairs, as `{a, b, distance}` objects.  Distances are
+    /// BigInts, since they can be too big for a Number.
+    pub fn closest_pairs(&self, k: usize, expansion: u64) -> Result<JsValue, JsValue> {
+        let pairs = self
+            .0
+            .closest_pairs(k, expansion)
+            .map_err(|e| JsValue::from(e.to_string()))?
+            .into_iter()
+            .map(Pair::from)
+            .collect::<Vec<_>>();
+        to_value(&pairs)
+    }
+
+    pub fn farthest_pair(&self, expansion: u64) -> Result<JsValue, JsValue> {
+        let pair = self
+            .0
+            .farthest_pair(expansion)
+            .map_err(|e| JsValue::from(e.to_string()))?;
+        to_value(&pair.map(Pair::from))
+    }
+}
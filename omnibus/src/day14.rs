@@ -0,0 +1,60 @@
+use serde::Serialize;
+use wasm_bindgen::prelude::*;
+
+crate::common_day!(day_14, Day14, u64, u64);
+
+#[derive(Serialize)]
+struct Frame {
+    /// The spin cycle this tilt is part of, counting from 1, or 0 for the
+    /// layout before any tilting.
+    cycle: u64,
+    direction: Option<String>,
+    layout: String,
+    north_load: u64,
+}
+
+#[derive(Serialize)]
+struct Cycle {
+    start: u64,
+    length: u64,
+}
+
+/// Every frame holds a copy of the whole layout, so don't let a caller ask
+/// for more of them than a page could want to animate.
+const MAX_CYCLES: u64 = 200;
+
+#[wasm_bindgen]
+impl Day14 {
+    /// The layout before tilting, and then after each tilt of the first
+    /// `cycles` spin cycles, up to 200 of them.
+    pub fn frames(&self, cycles: u64) -> JsValue {
+        let cycles = cycles.min(MAX_CYCLES);
+        let mut platform = self.0.platform().clone();
+        let mut frames = vec![Frame {
+            cycle: 0,
+            direction: None,
+            layout: platform.to_string(),
+            north_load: platform.north_load(),
+        }];
+
+        for cycle in 1..=cycles {
+            for direction in day_14::Direction::SPIN {
+                platform.tilt(direction);
+                frames.push(Frame {
+                    cycle,
+                    direction: Some(format!("{direction:?}")),
+                    layout: platform.to_string(),
+                    north_load: platform.north_load(),
+                });
+            }
+        }
+
+        serde_wasm_bindgen::to_value(&frames).unwrap()
+    }
+
+    /// When the layouts after each spin cycle start repeating, and how often.
+    pub fn find_cycle(&self) -> JsValue {
+        let (start, length) = self.0.platform().find_cycle();
+        serde_wasm_bindgen::to_value(&Cycle { start, length }).unwrap()
+    }
+}
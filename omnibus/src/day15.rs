@@ -0,0 +1,11 @@
+use wasm_bindgen::prelude::*;
+
+crate::common_day!(day_15, Day15, u64, u64);
+
+#[wasm_bindgen]
+impl Day15 {
+    /// The boxes after every step, the way the puzzle lists them.
+    pub fn trace(&self) -> Result<String, JsValue> {
+        self.0.trace().map_err(|e| JsValue::from(e.to_string()))
+    }
+}
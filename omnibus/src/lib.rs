@@ -1,6 +1,12 @@
 use wasm_bindgen::prelude::*;
 
+mod day05;
+mod day07;
 mod day10;
+mod day11;
+mod day14;
+mod day15;
+mod logs;
 
 macro_rules! common_day {
     ($krate: ident, $strukt: ident, $part1_result: ty, $part2_result: ty) => {
@@ -30,16 +36,11 @@ common_day!(day_01, Day01, u64, u64);
 common_day!(day_02, Day02, u64, u64);
 common_day!(day_03, Day03, u64, u64);
 common_day!(day_04, Day04, u64, u64);
-common_day!(day_05, Day05, u64, u64);
 common_day!(day_06, Day06, u64, u64);
-common_day!(day_07, Day07, u64, u64);
 common_day!(day_08, Day08, u64, u64);
 common_day!(day_09, Day09, u64, u64);
-common_day!(day_11, Day11, u64, u64);
 common_day!(day_12, Day12, u64, u64);
 common_day!(day_13, Day13, u64, u64);
-common_day!(day_14, Day14, u64, u64);
-common_day!(day_15, Day15, u64, u64);
 common_day!(day_16, Day16, u64, u64);
 common_day!(day_17, Day17, u64, u64);
 common_day!(day_18, Day18, u64, u64);
@@ -48,4 +49,5 @@ common_day!(day_19, Day19, u64, u64);
 #[wasm_bindgen(start)]
 pub fn start() {
     prelude::init();
+    prelude::diagnostics::init_buffered();
 }
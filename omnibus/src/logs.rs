@@ -0,0 +1,43 @@
+use prelude::diagnostics;
+use serde::Serialize;
+use wasm_bindgen::prelude::*;
+
+#[derive(Serialize)]
+struct Record {
+    level: String,
+    target: String,
+    message: String,
+}
+
+#[derive(Serialize)]
+struct Logs {
+    records: Vec<Record>,
+    dropped: usize,
+}
+
+/// Start collecting `day`'s diagnostics, e.g. `day_08`, at the given verbosity
+/// (0 for warnings only, 1 for debug, 2 for trace).
+#[wasm_bindgen]
+pub fn set_log_verbosity(day: &str, verbosity: u8) {
+    diagnostics::set_buffer_filter(diagnostics::filter_for(day, verbosity));
+}
+
+/// Everything logged since the last call, as `{ records: [{ level, target,
+/// message }], dropped }`.
+#[wasm_bindgen]
+pub fn take_logs() -> JsValue {
+    let (records, dropped) = diagnostics::take_buffered();
+    let logs = Logs {
+        records: records
+            .into_iter()
+            .map(|r| Record {
+                level: r.level.to_string(),
+                target: r.target,
+                message: r.message,
+            })
+            .collect(),
+        dropped,
+    };
+
+    serde_wasm_bindgen::to_value(&logs).unwrap()
+}
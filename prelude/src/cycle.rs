@@ -0,0 +1,153 @@
+//! Finding where a sequence x₀, f(x₀), f(f(x₀)), ... starts repeating itself.
+//!
+//! Each detector returns `(mu, lambda)`: the state at step `mu` is the first
+//! one that ever comes around again, and it does so every `lambda` steps.  They
+//! all loop forever if the sequence never repeats.
+
+use std::hash::Hash;
+
+use crate::HashMap;
+
+/// Remember every state, which takes the fewest calls to `f` but the most
+/// memory.
+pub fn hashed<T: Clone + Eq + Hash>(start: T, mut f: impl FnMut(&T) -> T) -> (u64, u64) {
+    let mut seen = HashMap::new();
+    let mut state = start;
+    for i in 0.. {
+        if let Some(&mu) = seen.get(&state) {
+            return (mu, i - mu);
+        }
+        let next = f(&state);
+        seen.insert(state, i);
+        state = next;
+    }
+    unreachable!()
+}
+
+/// Floyd's tortoise and hare, which keeps only a few states at a time.
+pub fn floyd<T: Clone + Eq>(start: T, mut f: impl FnMut(&T) -> T) -> (u64, u64) {
+    // the hare gains a step on the tortoise each time, so they meet once the
+    // tortoise is in the cycle and the gap is a multiple of lambda
+    let mut tortoise = f(&start);
+    let mut hare = f(&tortoise);
+    while tortoise != hare {
+        tortoise = f(&tortoise);
+        hare = f(&hare);
+        hare = f(&hare);
+    }
+
+    // which leaves them a multiple of lambda apart, so walking both at the
+    // same speed from mu steps before the cycle brings them together at mu
+    let mut mu = 0;
+    tortoise = start;
+    while tortoise != hare {
+        tortoise = f(&tortoise);
+        hare = f(&hare);
+        mu += 1;
+    }
+
+    let mut lambda = 1;
+    hare = f(&tortoise);
+    while tortoise != hare {
+        hare = f(&hare);
+        lambda += 1;
+    }
+
+    (mu, lambda)
+}
+
+/// Brent's algorithm, which keeps only a few states at a time like
+/// [`floyd`], but usually calls `f` fewer times.
+pub fn brent<T: Clone + Eq>(start: T, mut f: impl FnMut(&T) -> T) -> (u64, u64) {
+    // the tortoise teleports to the hare after every power of two steps, so
+    // the hare catches it within one lap once the power is at least lambda
+    let mut power = 1;
+    let mut lambda = 1;
+    let mut tortoise = start.clone();
+    let mut hare = f(&start);
+    while tortoise != hare {
+        if power == lambda {
+            tortoise = hare.clone();
+            power *= 2;
+            lambda = 0;
+        }
+        hare = f(&hare);
+        lambda += 1;
+    }
+
+    // then a hare exactly lambda steps ahead meets the tortoise at mu
+    let mut mu = 0;
+    tortoise = start.clone();
+    hare = start;
+    for _ in 0..lambda {
+        hare = f(&hare);
+    }
+    while tortoise != hare {
+        tortoise = f(&tortoise);
+        hare = f(&hare);
+        mu += 1;
+    }
+
+    (mu, lambda)
+}
+
+/// The earliest step whose state is the same as the one at step `n`.
+pub fn earliest_equivalent((mu, lambda): (u64, u64), n: u64) -> u64 {
+    if n < mu {
+        n
+    } else {
+        mu + (n - mu) % lambda
+    }
+}
+
+/// The state at step `n`, taking at most `mu + lambda` steps to get there.
+pub fn state_at<T>(start: T, mut f: impl FnMut(&T) -> T, cycle: (u64, u64), n: u64) -> T {
+    let mut state = start;
+    for _ in 0..earliest_equivalent(cycle, n) {
+        state = f(&state);
+    }
+    state
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn detectors_agree() {
+        for modulus in 1..60u64 {
+            let f = |&x: &u64| (x * x + 1) % modulus;
+            for start in 0..modulus {
+                // the slow way: the first state to repeat
+                let mut history = vec![start];
+                let expected = loop {
+                    let next = f(history.last().unwrap());
+                    if let Some(mu) = history.iter().position(|&x| x == next) {
+                        break (mu as u64, (history.len() - mu) as u64);
+                    }
+                    history.push(next);
+                };
+
+                assert_eq!(hashed(start, f), expected, "{start} mod {modulus}");
+                assert_eq!(floyd(start, f), expected, "{start} mod {modulus}");
+                assert_eq!(brent(start, f), expected, "{start} mod {modulus}");
+
+                for n in [0, 1, 5, 100, 1_000_000_000_000] {
+                    let mut direct = start;
+                    for _ in 0..n.min(1000) {
+                        direct = f(&direct);
+                    }
+                    if n <= 1000 {
+                        assert_eq!(state_at(start, f, expected, n), direct);
+                    }
+                    let earliest = earliest_equivalent(expected, n);
+                    assert!(earliest < expected.0 + expected.1);
+                    assert_eq!(
+                        state_at(start, f, expected, n),
+                        state_at(start, f, expected, earliest)
+                    );
+                }
+            }
+        }
+    }
+}
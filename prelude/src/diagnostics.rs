@@ -0,0 +1,195 @@
+//! Every day reports what it's up to through `tracing` events, whose target is
+//! the day's crate name (e.g. `day_05`).  That lets one day's chatter be turned
+//! on without drowning in everybody else's.
+//!
+//! The CLI prints events to stderr, and the web page collects them into a
+//! buffer that it can display.
+
+use std::{collections::VecDeque, fmt::Write, str::FromStr, sync::Mutex};
+
+use tracing::{
+    field::{Field, Visit},
+    subscriber::Interest,
+    Event, Level, Metadata, Subscriber,
+};
+use tracing_subscriber::{
+    filter::{LevelFilter, Targets},
+    layer::{Context, SubscriberExt},
+    util::SubscriberInitExt,
+    Layer,
+};
+
+/// Warnings from everything, plus debug events (or trace events, when
+/// `verbosity` is 2 or more) from `day`.
+pub fn filter_for(day: &str, verbosity: u8) -> Targets {
+    let level = match verbosity {
+        0 => LevelFilter::WARN,
+        1 => LevelFilter::DEBUG,
+        _ => LevelFilter::TRACE,
+    };
+
+    Targets::new()
+        .with_default(LevelFilter::WARN)
+        .with_target(day, level)
+}
+
+/// Print events to stderr.  `RUST_LOG`, if it is set, replaces `filter`, e.g.
+/// `RUST_LOG=day_08=trace`.
+pub fn init_stderr(filter: Targets) {
+    let filter = std::env::var("RUST_LOG")
+        .ok()
+        .and_then(|env| Targets::from_str(&env).ok())
+        .unwrap_or(filter);
+
+    tracing_subscriber::fmt()
+        .with_writer(std::io::stderr)
+        .with_max_level(LevelFilter::TRACE)
+        .without_time()
+        .finish()
+        .with(filter)
+        .init();
+}
+
+/// Print events from tests, in a way that `cargo test` will capture.  Safe to
+/// call more than once.
+pub fn init_test() {
+    let _ = tracing_subscriber::fmt()
+        .with_test_writer()
+        .with_max_level(Level::DEBUG)
+        .try_init();
+}
+
+#[derive(Clone, Debug)]
+pub struct Record {
+    pub level: Level,
+    pub target: String,
+    /// The event's message followed by its other fields, as `key=value`.
+    pub message: String,
+}
+
+/// Some days emit an event for every step of a very long walk, so only this
+/// many of the newest records are kept.
+const BUFFER_CAPACITY: usize = 10_000;
+
+struct Buffer {
+    filter: Targets,
+    records: VecDeque<Record>,
+    dropped: usize,
+}
+
+static BUFFER: Mutex<Option<Buffer>> = Mutex::new(None);
+
+struct BufferLayer;
+
+impl<S: Subscriber> Layer<S> for BufferLayer {
+    fn register_callsite(&self, _metadata: &'static Metadata<'static>) -> Interest {
+        // the filter can change at any time, so ask about every event
+        Interest::sometimes()
+    }
+
+    fn enabled(&self, metadata: &Metadata<'_>, _ctx: Context<'_, S>) -> bool {
+        BUFFER
+            .lock()
+            .unwrap()
+            .as_ref()
+            .is_some_and(|b| b.filter.would_enable(metadata.target(), metadata.level()))
+    }
+
+    fn on_event(&self, event: &Event<'_>, _ctx: Context<'_, S>) {
+        let mut visitor = MessageVisitor::default();
+        event.record(&mut visitor);
+
+        let mut buffer = BUFFER.lock().unwrap();
+        let Some(buffer) = buffer.as_mut() else {
+            return;
+        };
+
+        if buffer.records.len() == BUFFER_CAPACITY {
+            buffer.records.pop_front();
+            buffer.dropped += 1;
+        }
+        buffer.records.push_back(Record {
+            level: *event.metadata().level(),
+            target: event.metadata().target().to_owned(),
+            message: (visitor.message + &visitor.fields).trim_start().to_owned(),
+        });
+    }
+}
+
+#[derive(Default)]
+struct MessageVisitor {
+    message: String,
+    fields: String,
+}
+
+impl Visit for MessageVisitor {
+    fn record_debug(&mut self, field: &Field, value: &dyn std::fmt::Debug) {
+        if field.name() == "message" {
+            let _ = write!(self.message, "{value:?}");
+        } else {
+            let _ = write!(self.fields, " {}={value:?}", field.name());
+        }
+    }
+}
+
+/// Collect events into a buffer instead of printing them; see
+/// [`take_buffered`].  Nothing is collected until [`set_buffer_filter`] is
+/// called.
+pub fn init_buffered() {
+    *BUFFER.lock().unwrap() = Some(Buffer {
+        filter: Targets::new(),
+        records: VecDeque::new(),
+        dropped: 0,
+    });
+
+    let _ = tracing_subscriber::registry().with(BufferLayer).try_init();
+}
+
+pub fn set_buffer_filter(filter: Targets) {
+    if let Some(buffer) = BUFFER.lock().unwrap().as_mut() {
+        buffer.filter = filter;
+    }
+}
+
+/// Everything that has been collected since the last call, and how many older
+/// records were thrown away to make room.
+pub fn take_buffered() -> (Vec<Record>, usize) {
+    match BUFFER.lock().unwrap().as_mut() {
+        Some(buffer) => (
+            buffer.records.drain(..).collect(),
+            std::mem::take(&mut buffer.dropped),
+        ),
+        None => (vec![], 0),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn buffered() {
+        init_buffered();
+
+        tracing::debug!(target: "day_99", "too early");
+        set_buffer_filter(filter_for("day_99", 1));
+        tracing::debug!(target: "day_99", x = 1, "found");
+        tracing::trace!(target: "day_99", "too verbose");
+        tracing::debug!(target: "day_98", "wrong day");
+        tracing::warn!(target: "day_98", y = ?(2, 3));
+
+        let (records, dropped) = take_buffered();
+        assert_eq!(dropped, 0);
+        assert_eq!(
+            records
+                .iter()
+                .map(|r| (r.level, r.target.as_str(), r.message.as_str()))
+                .collect::<Vec<_>>(),
+            vec![
+                (Level::DEBUG, "day_99", "found x=1"),
+                (Level::WARN, "day_98", "y=(2, 3)"),
+            ]
+        );
+        assert!(take_buffered().0.is_empty());
+    }
+}
@@ -5,6 +5,12 @@ pub use prelude_2022::*;
 pub extern crate anyhow;
 pub use anyhow::Context;
 
+pub extern crate tracing;
+
+pub mod cycle;
+pub mod diagnostics;
+pub mod math;
+
 pub trait Day {
     fn new(input: &str) -> Self;
     fn part1(&self) -> anyhow::Result<u64>;
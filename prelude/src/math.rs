@@ -0,0 +1,170 @@
+//! Number theory that comes up whenever a puzzle boils down to several cycles
+//! needing to line up.
+
+use std::fmt;
+
+pub fn gcd(a: u64, b: u64) -> u64 {
+    if b == 0 {
+        a
+    } else {
+        gcd(b, a % b)
+    }
+}
+
+/// Returns `(g, x, y)` such that `a * x + b * y = g`, where `g` is the
+/// non-negative greatest common divisor of `a` and `b`.
+pub fn extended_gcd(a: i128, b: i128) -> (i128, i128, i128) {
+    if b == 0 {
+        if a < 0 {
+            (-a, -1, 0)
+        } else {
+            (a, 1, 0)
+        }
+    } else {
+        let (g, x, y) = extended_gcd(b, a % b);
+        (g, y, x - (a / b) * y)
+    }
+}
+
+/// The `x` in `0..m` such that `a * x = 1 (mod m)`, if there is one.
+pub fn mod_inverse(a: u64, m: u64) -> Option<u64> {
+    if m == 0 {
+        return None;
+    }
+
+    let (g, x, _y) = extended_gcd(a as i128, m as i128);
+    if g != 1 {
+        return None;
+    }
+
+    Some(x.rem_euclid(m as i128) as u64)
+}
+
+/// The least common multiple of `a` and `b`, or `None` if it doesn't fit in a
+/// `u64`.
+pub fn checked_lcm(a: u64, b: u64) -> Option<u64> {
+    if a == 0 || b == 0 {
+        return Some(0);
+    }
+
+    (a / gcd(a, b)).checked_mul(b)
+}
+
+/// The least common multiple of everything in `it`, or `None` if it doesn't fit
+/// in a `u64`.  The least common multiple of nothing at all is 1.
+pub fn checked_lcm_all(it: impl IntoIterator<Item = u64>) -> Option<u64> {
+    it.into_iter().try_fold(1, checked_lcm)
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum CrtError {
+    /// No number satisfies both `x = a (mod m)` and `x = b (mod n)`.
+    Inconsistent { a: u64, m: u64, b: u64, n: u64 },
+    /// The combined modulus doesn't fit in a `u64`.
+    Overflow,
+}
+
+impl fmt::Display for CrtError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CrtError::Inconsistent { a, m, b, n } => write!(
+                f,
+                "x = {a} (mod {m}) and x = {b} (mod {n}) have no common solution"
+            ),
+            CrtError::Overflow => write!(f, "the combined modulus overflows a u64"),
+        }
+    }
+}
+
+impl std::error::Error for CrtError {}
+
+/// Solve the system of congruences `x = residue (mod modulus)`, where the
+/// moduli need not be coprime.
+///
+/// Returns `(residue, modulus)` such that every solution is `residue (mod
+/// modulus)`, with `residue < modulus`.  An empty system is solved by anything,
+/// i.e. `(0, 1)`.
+///
+/// # Panics
+///
+/// Panics if any modulus is zero.
+pub fn crt(congruences: impl IntoIterator<Item = (u64, u64)>) -> Result<(u64, u64), CrtError> {
+    congruences
+        .into_iter()
+        .try_fold((0, 1), |(a, m), (b, n)| crt_pair(a, m, b % n, n))
+}
+
+// Combine x = a (mod m) and x = b (mod n) into a single x = c (mod lcm(m, n)).
+fn crt_pair(a: u64, m: u64, b: u64, n: u64) -> Result<(u64, u64), CrtError> {
+    assert!(m != 0 && n != 0, "modulus of zero");
+
+    let (g, p, _q) = extended_gcd(m as i128, n as i128);
+    let diff = b as i128 - a as i128;
+    if diff % g != 0 {
+        return Err(CrtError::Inconsistent { a, m, b, n });
+    }
+
+    let lcm = checked_lcm(m, n).ok_or(CrtError::Overflow)?;
+
+    // m * p = g (mod n), so a + m * p * (diff / g) = b (mod n)
+    let n_g = n as i128 / g;
+    let k = (diff / g).rem_euclid(n_g) as u128 * p.rem_euclid(n_g) as u128 % n_g as u128;
+    // a < m and k < n / g, so this is already less than lcm
+    let c = a as u128 + m as u128 * k;
+
+    Ok((c as u64, lcm))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::Itertools;
+
+    #[test]
+    fn inverse() {
+        assert_eq!(mod_inverse(3, 7), Some(5));
+        assert_eq!(mod_inverse(4, 8), None);
+        assert_eq!(mod_inverse(1, 1), Some(0));
+    }
+
+    #[test]
+    fn lcm() {
+        assert_eq!(checked_lcm_all([4, 6, 10]), Some(60));
+        assert_eq!(checked_lcm_all([]), Some(1));
+        assert_eq!(checked_lcm_all([u64::MAX, u64::MAX - 1]), None);
+    }
+
+    #[test]
+    fn chinese_remainder() {
+        assert_eq!(crt([(2, 3), (3, 5), (2, 7)]), Ok((23, 105)));
+        assert_eq!(crt([(2, 4), (4, 6)]), Ok((10, 12)));
+        assert_eq!(crt([]), Ok((0, 1)));
+        assert_eq!(
+            crt([(1, 4), (2, 6)]),
+            Err(CrtError::Inconsistent {
+                a: 1,
+                m: 4,
+                b: 2,
+                n: 6
+            })
+        );
+        assert_eq!(
+            crt([(0, u64::MAX), (1, u64::MAX - 1)]),
+            Err(CrtError::Overflow)
+        );
+
+        // brute-force everything small
+        for (m, n) in (1..20).cartesian_product(1..20) {
+            for (a, b) in (0..m).cartesian_product(0..n) {
+                let expected = (0..m * n).find(|x| x % m == a && x % n == b);
+                match crt([(a, m), (b, n)]) {
+                    Ok((x, l)) => {
+                        assert_eq!(Some(x), expected);
+                        assert_eq!(l, checked_lcm(m, n).unwrap());
+                    }
+                    Err(_) => assert_eq!(expected, None),
+                }
+            }
+        }
+    }
+}